@@ -0,0 +1,53 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+//
+// Arguments of the verbs that are not Josev messages, all optional:
+// a verb called without argument gets the default value
+//
+
+use crate::josev;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct MqttConfigArgs {
+    // one configuration per topic prefix, a single unprefixed one when empty
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    pub host: Option<String>,
+    #[schemars(range(min = 1, max = 65535))]
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct TransactionArgs {
+    // the configured EVSE when missing
+    pub evse_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct AvailabilityArgs {
+    // only read the availability when missing
+    pub operational_status: Option<josev::OperationalStatus>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct EmergencyStopArgs {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ForceCpStateArgs {
+    pub cp: Option<josev::ControlPilotState>,
+    pub closed_contactor: Option<bool>,
+}
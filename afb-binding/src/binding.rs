@@ -10,54 +10,217 @@
  *
  */
 
-use std::sync::{Arc, RwLock};
-
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::args::*;
+use crate::compat::{self, Compatibility, Dialect, UnknownVersionPolicy, VersionRange};
+use crate::health::*;
+use crate::info::{api_info, schema, VerbDesc};
 use crate::josev;
+use crate::messages::*;
+use crate::mqtt_config::*;
+use crate::registry::*;
+use crate::session::*;
+use crate::subcall::{self, SubcallPolicy};
 use afbv4::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use typesv4::prelude::*;
 
+//
+// Minimal variation of the CP measurements before a new cp_status is pushed
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct CpDeadband {
+    // volts
+    #[serde(default = "CpDeadband::default_voltage")]
+    voltage: f32,
+    // percent
+    #[serde(default = "CpDeadband::default_duty_cycle")]
+    duty_cycle: f32,
+}
+
+impl CpDeadband {
+    fn default_voltage() -> f32 {
+        0.5
+    }
+
+    fn default_duty_cycle() -> f32 {
+        1.0
+    }
+
+    fn default() -> Self {
+        CpDeadband {
+            voltage: Self::default_voltage(),
+            duty_cycle: Self::default_duty_cycle(),
+        }
+    }
+
+    // true when the new measure is far enough from the last pushed one
+    fn exceeded(&self, previous: &Option<CpMeasure>, measure: &CpMeasure) -> bool {
+        match previous {
+            None => true,
+            Some(previous) => {
+                (previous.max_voltage - measure.max_voltage).abs() > self.voltage
+                    || (previous.min_voltage - measure.min_voltage).abs() > self.voltage
+                    || (previous.duty_cycle - measure.duty_cycle).abs() > self.duty_cycle
+            }
+        }
+    }
+}
+
+//
+// Session states at which the contactor may be closed or opened,
+// in the order they happen during an HLC session
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ContactorTrigger {
+    ScheduleExchange,
+    PowerDelivery,
+    SessionStop,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct ContactorRule {
+    close: ContactorTrigger,
+    open: ContactorTrigger,
+}
+
+//
+// When to close and open the contactor, depending on the negotiated protocol
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct ContactorPolicy {
+    #[serde(default = "ContactorPolicy::default_iso2")]
+    iso2: ContactorRule,
+    #[serde(default = "ContactorPolicy::default_iso20")]
+    iso20: ContactorRule,
+}
+
+impl ContactorPolicy {
+    // ISO 15118-2 and DIN 70121: closed by PowerDeliveryReq(Start), opened by PowerDeliveryReq(Stop)
+    fn default_iso2() -> ContactorRule {
+        ContactorRule {
+            close: ContactorTrigger::PowerDelivery,
+            open: ContactorTrigger::PowerDelivery,
+        }
+    }
+
+    // ISO 15118-20: the contactor must already be closed when PowerDeliveryReq arrives,
+    // ScheduleExchange is the state just before PowerDelivery
+    fn default_iso20() -> ContactorRule {
+        ContactorRule {
+            close: ContactorTrigger::ScheduleExchange,
+            open: ContactorTrigger::PowerDelivery,
+        }
+    }
+
+    fn default() -> Self {
+        ContactorPolicy {
+            iso2: Self::default_iso2(),
+            iso20: Self::default_iso20(),
+        }
+    }
+
+    fn rule(&self, iso_state: Option<IsoState>) -> ContactorRule {
+        match iso_state {
+            Some(IsoState::Iso20) | Some(IsoState::Iso20Discharge) => self.iso20,
+            // DIN 70121 is not reported as an iso state and follows ISO 15118-2
+            _ => self.iso2,
+        }
+    }
+
+    // the contactor is closed at the configured state or at any later closing point
+    fn should_close(&self, iso_state: Option<IsoState>, trigger: ContactorTrigger) -> bool {
+        trigger >= self.rule(iso_state).close
+    }
+
+    fn should_open(&self, iso_state: Option<IsoState>, trigger: ContactorTrigger) -> bool {
+        trigger >= self.rule(iso_state).open
+    }
+}
+
+//
+// Availability of the EVSE, as requested by the CSMS
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Availability {
+    Operative,
+    // out of service, an ongoing session is stopped
+    Inoperative,
+    // out of service once the ongoing session is over
+    Scheduled,
+}
+
+//
+// Timeouts and retries of the subcalls toward each linked api
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct SubcallPolicies {
+    #[serde(default)]
+    charge_api: SubcallPolicy,
+    #[serde(default = "SubcallPolicy::reads")]
+    meter_api: SubcallPolicy,
+    #[serde(default)]
+    auth_api: SubcallPolicy,
+}
+
+impl Default for SubcallPolicies {
+    fn default() -> Self {
+        SubcallPolicies {
+            charge_api: SubcallPolicy::default(),
+            meter_api: SubcallPolicy::reads(),
+            auth_api: SubcallPolicy::default(),
+        }
+    }
+}
+
+//
+// Josev liveness detection
+#[derive(Clone, Copy, Debug)]
+struct Watchdog {
+    // silence of Josev (ms) after which it is considered gone
+    timeout: u32,
+    // to_mqtt verb sent to Josev once it has been quiet for half the timeout
+    ping: Option<&'static str>,
+}
+
+impl Watchdog {
+    fn period(&self) -> u32 {
+        (self.timeout / 4).max(100)
+    }
+}
+
 #[derive(Clone)]
 pub struct ApiUserData {
     charge_api: &'static str,
     auth_api: &'static str,
     meter_api: &'static str,
 
-    evse_id: String,
-}
+    // optional IEC 61851 api providing CP measurements
+    iec_api: Option<&'static str>,
+    cp_deadband: CpDeadband,
+    // verb of the IEC api holding the CP in state F (true) or releasing it (false)
+    cp_fault_verb: Option<&'static str>,
 
-impl AfbApiControls for ApiUserData {
-    // the API is created and ready. At this level user may subcall api(s) declare as dependencies
-    fn start(&mut self, api: &AfbApi) -> Result<(), AfbError> {
-        println!("== JOSEV binding starting");
+    contactor_policy: ContactorPolicy,
 
-        // Subscribe to IEC events
-        AfbSubCall::call_sync(api, self.charge_api, "subscribe", true)?;
+    subcalls: SubcallPolicies,
 
-        // Reset authentication
-        if let Err(_err) = AfbSubCall::call_sync(api, self.auth_api, "logout", 0) {
-            afb_log_msg!(
-                Notice,
-                api.get_apiv4(),
-                "**logout failed**, probably already logged out"
-            );
-        }
+    // time given to the contactor to open on stop_charging (ms)
+    stop_charging_timeout: u32,
 
-        // Subscribe to MQTT iso15118_state_info (for authorization)
-        let subscribed_messages = JsoncObj::array();
-        subscribed_messages.append("iso15118_state_info")?;
-        subscribed_messages.append("iso15118_charge_limit")?;
-        subscribed_messages.append("hlc_charging")?;
-        subscribed_messages.append("transaction_status")?;
-        subscribed_messages.append("slac_status")?;
-        subscribed_messages.append("service_status")?;
-        AfbSubCall::call_sync(api, "from_mqtt", "subscribe_events", subscribed_messages)?;
-        Ok(())
-    }
+    // let an ongoing session finish when the EVSE is set inoperative
+    finish_session_when_inoperative: bool,
 
-    // mandatory unsed declaration
-    fn as_any(&mut self) -> &mut dyn Any {
-        self
-    }
+    unknown_api_version: UnknownVersionPolicy,
+    // api versions the station was validated with
+    api_versions: Vec<VersionRange>,
+
+    watchdog: Option<Watchdog>,
+
+    // Josev messages subscribed to
+    josev_messages: Vec<&'static str>,
+
+    evse_id: String,
 }
 
 //
@@ -68,17 +231,44 @@ struct Context {
     contactor_status_event: &'static AfbEvent,
     cs_status_and_limits_event: &'static AfbEvent,
 
-    // current charging state
-    charging_state: josev::ControlPilotState,
+    // charging session state machine
+    session: Session,
+
+    // last CP measurements pushed to Josev
+    cp_measure: Option<CpMeasure>,
+
+    // cable lock state, the connector is unlocked only once the contactor is open
+    connector_locked: bool,
+    unlock_pending: bool,
+    lock_fault: bool,
 
-    // current contactor state
-    contactor_closed: bool,
+    // reason of an emergency stop, until the EVSE is re-armed
+    emergency: Option<String>,
+    // Josev is told the CP is in state F: held there by the IEC api,
+    // or without CP fault verb, the contactor being open
+    cp_fault: bool,
+    // state F to report once the contactor is confirmed open
+    cp_fault_pending: bool,
 
-    // selected iso state
-    iso_state: Option<IsoState>,
+    availability: Availability,
 
-    // selected payment mode of the charging session (EIM, PnC)
-    payment_option: Option<PaymentOption>,
+    // the binder is stopping
+    shutdown: bool,
+
+    // Josev services health
+    services: Services,
+
+    // schema variant of the running iso15118 stack, when known
+    dialect: Option<Dialect>,
+    // services running an api version the binding cannot work with
+    incompatible: HashSet<josev::Service>,
+
+    // last message (event, request or ping reply) received from Josev
+    last_josev_message: Instant,
+    // the watchdog tripped, the station runs without the ISO stack
+    josev_lost: bool,
+    // since when Josev is expected to talk, only during HLC sessions
+    josev_watched_since: Option<Instant>,
 
     // static parameters of the charging station
     cs_parameters: josev::CsParametersResponse,
@@ -86,13 +276,225 @@ struct Context {
     // current electrical state of the station
     cs_status_and_limits: josev::CsStatusAndLimitsResponse,
 
+    // configured status and AC current limits, before any session limitation
+    evse_status_code: josev::CsStatusAndLimitsStatusCode,
+    evse_max_current: Option<josev::AcMaxCurrent>,
+
+    // current limit of the authorized user (smart card)
+    auth_max_current: Option<f32>,
+
+    // current capacity of the plugged cable, detected from the proximity pilot
+    // (0 when the PP resistor is invalid)
+    cable_max_current: Option<f32>,
+
     device_model: Option<josev::DeviceModelResponse>,
 
+    // current (or last) transaction of each EVSE
+    transactions: HashMap<String, TransactionRecord>,
+
+    // stop_charging requests waiting for the contactor to open
+    open_waiters: Vec<OpenWaiter>,
+    // relay state last reported by the IEC api
+    relay_closed: Option<bool>,
+    // contactor state Josev was last told about
+    contactor_pushed: Option<bool>,
+    last_waiter: u64,
+
     // for debugging
     forced_charging_state: Option<josev::ControlPilotState>,
     forced_contactor_closed: Option<bool>,
 }
 
+//
+// A session that cannot be saved goes on, it only would not survive a restart
+fn session_saved(api: AfbApiV4, result: Result<(), AfbError>) {
+    if let Err(error) = result {
+        afb_log_msg!(Warning, api, "{}", error);
+    }
+}
+
+impl Context {
+    //
+    // Move the session to a new state, invalid transitions are logged and ignored.
+    // Returns false when the transition was refused, its side effects must then be skipped.
+    fn enter(&mut self, api: AfbApiV4, to: SessionState, cause: &'static str) -> bool {
+        match self.session.transition(to, cause) {
+            Ok(true) => {
+                afb_log_msg!(Debug, api, "Session state {:?} ({})", to, cause);
+                if let Err(error) = self.session.save() {
+                    afb_log_msg!(Warning, api, "{}", error);
+                }
+                // the session allowed to finish is over, the EVSE is now out of service
+                if self.availability == Availability::Scheduled && !self.session.is_active() {
+                    afb_log_msg!(Notice, api, "EVSE availability Inoperative (session over)");
+                    self.availability = Availability::Inoperative;
+                }
+                // the status of an inoperative EVSE depends on the session
+                if self.availability != Availability::Operative && self.apply_status_and_limits() {
+                    self.cs_status_and_limits_event
+                        .push(self.cs_status_and_limits.clone());
+                }
+                true
+            }
+            Ok(false) => true,
+            Err(error) => {
+                afb_log_msg!(Warning, api, "{}", error);
+                false
+            }
+        }
+    }
+
+    fn cp_status_update(&self, state: josev::ControlPilotState) -> josev::CpStatusUpdate {
+        josev::CpStatusUpdate {
+            evse_id: self.cs_parameters.parameters[0].evse_id.clone(),
+            connector_id: self.cs_parameters.parameters[0].connectors[0].id,
+            state,
+            max_voltage: self.cp_measure.map(|measure| measure.max_voltage),
+            min_voltage: self.cp_measure.map(|measure| measure.min_voltage),
+            // a measure, which may slightly overshoot the 0-100 range
+            duty_cycle: self.cp_measure.map(|measure| measure.duty_cycle.clamp(0.0, 100.0)),
+        }
+    }
+
+    fn contactor_status_update(&self, closed: bool) -> josev::CsContactorStatusUpdate {
+        josev::CsContactorStatusUpdate {
+            evse_id: self.cs_parameters.parameters[0].evse_id.clone(),
+            status: {
+                if closed {
+                    josev::CsContactorStatusResponseStatus::Closed
+                } else {
+                    josev::CsContactorStatusResponseStatus::Opened
+                }
+            },
+            info: None,
+        }
+    }
+
+    // tell Josev about the contactor state, unless it already knows it:
+    // both the charge manager and the relay feedback report the same change
+    fn push_contactor_status(&mut self, closed: bool) {
+        if self.contactor_pushed != Some(closed) {
+            self.contactor_pushed = Some(closed);
+            self.contactor_status_event
+                .push(self.contactor_status_update(closed));
+        }
+    }
+
+    // without CP fault verb, report state F once the contactor is confirmed open
+    fn report_cp_fault(&mut self) {
+        if self.cp_fault_pending && !self.relay_closed.unwrap_or(self.session.contactor_closed()) {
+            self.cp_fault_pending = false;
+            self.cp_fault = true;
+            self.cp_status_event.push(self.cp_status_update(self.cp_state()));
+        }
+    }
+
+    // a cable is plugged but its proximity pilot is invalid
+    fn pp_fault(&self) -> bool {
+        !matches!(self.session.cp_state(), josev::ControlPilotState::A1)
+            && matches!(self.cable_max_current, Some(imax) if imax <= 0.0)
+    }
+
+    fn status_code(&self) -> josev::CsStatusAndLimitsStatusCode {
+        if self.emergency.is_some() {
+            josev::CsStatusAndLimitsStatusCode::EvseEmergencyShutdown
+        } else if self.shutdown {
+            josev::CsStatusAndLimitsStatusCode::EvseShutdown
+        } else if self.pp_fault() || self.lock_fault {
+            josev::CsStatusAndLimitsStatusCode::EvseMalfunction
+        } else if !self.incompatible.is_empty() {
+            josev::CsStatusAndLimitsStatusCode::EvseNotReady
+        } else {
+            match self.availability {
+                Availability::Operative => self.evse_status_code,
+                Availability::Scheduled if self.session.is_active() => self.evse_status_code,
+                Availability::Inoperative if self.session.is_active() => {
+                    josev::CsStatusAndLimitsStatusCode::EvseShutdown
+                }
+                _ => josev::CsStatusAndLimitsStatusCode::EvseNotReady,
+            }
+        }
+    }
+
+    // no new session may start (nor an interrupted one go on)
+    fn refuses_sessions(&self) -> bool {
+        matches!(
+            self.status_code(),
+            josev::CsStatusAndLimitsStatusCode::EvseNotReady
+                | josev::CsStatusAndLimitsStatusCode::EvseShutdown
+                | josev::CsStatusAndLimitsStatusCode::EvseEmergencyShutdown
+        )
+    }
+
+    // Combine the configured, authorization and cable limits into the
+    // status and limits sent to Josev, returns true when they changed
+    fn apply_status_and_limits(&mut self) -> bool {
+        let mut changed = false;
+
+        let status_code = self.status_code();
+        if self.cs_status_and_limits.evses[0].status_code != status_code {
+            self.cs_status_and_limits.evses[0].status_code = status_code;
+            changed = true;
+        }
+
+        if let Some(evse_max_current) = &self.evse_max_current {
+            let limit = |configured: f32| {
+                [self.auth_max_current, self.cable_max_current]
+                    .into_iter()
+                    .flatten()
+                    .filter(|imax| *imax > 0.0)
+                    .fold(configured, f32::min)
+            };
+            let max_current = josev::AcMaxCurrent {
+                l1: limit(evse_max_current.l1),
+                l2: limit(evse_max_current.l2),
+                l3: limit(evse_max_current.l3),
+            };
+            if let Some(ac_limits) = &mut self.cs_status_and_limits.evses[0].ac {
+                if ac_limits.max_current != max_current {
+                    ac_limits.max_current = max_current;
+                    changed = true;
+                }
+            }
+        }
+
+        changed
+    }
+
+    // charging state as seen by Josev
+    fn cp_state(&self) -> josev::ControlPilotState {
+        if self.cp_fault {
+            josev::ControlPilotState::F
+        } else if let Some(forced) = self.forced_charging_state {
+            forced
+        } else {
+            self.session.cp_state()
+        }
+    }
+
+    //
+    // Josev lost its view of the station (service restart), send it everything again
+    fn resync(&self) {
+        self.cs_status_and_limits_event
+            .push(self.cs_status_and_limits.clone());
+        self.cp_status_event.push(self.cp_status_update(self.cp_state()));
+        let closed = self
+            .forced_contactor_closed
+            .unwrap_or(self.session.contactor_closed());
+        self.contactor_status_event
+            .push(self.contactor_status_update(closed));
+    }
+
+    fn josev_alive(&mut self) {
+        self.last_josev_message = Instant::now();
+    }
+
+    // the charging state seen by Josev does not follow the plug
+    fn cp_overridden(&self) -> bool {
+        self.cp_fault || self.forced_charging_state.is_some()
+    }
+}
+
 #[derive(Clone)]
 struct SharedContext {
     config: ApiUserData,
@@ -100,112 +502,914 @@ struct SharedContext {
     shared: Arc<RwLock<Context>>,
 }
 
+// Verbs called by Josev, the JOSEV_REQUESTS
+static JOSEV_VERBS: [(VerbDesc, VerbHandler); 8] = [
+    (
+        VerbDesc::new("cs_contactor_status", "Contactor state of an EVSE")
+            .request(schema::<josev::CsContactorStatusRequest>)
+            .response(schema::<josev::CsContactorStatusResponse>),
+        on_contactor_status,
+    ),
+    (
+        VerbDesc::new("cs_status_and_limits", "Status and limits of the EVSEs")
+            .response(schema::<josev::CsStatusAndLimitsResponse>),
+        on_status_and_limits,
+    ),
+    (
+        VerbDesc::new("cs_parameters", "Static parameters of the charging station")
+            .response(schema::<josev::CsParametersResponse>),
+        on_cs_parameters,
+    ),
+    (
+        VerbDesc::new("device_model", "OCPP device model of the charging station")
+            .response(schema::<josev::DeviceModelResponse>),
+        on_device_model,
+    ),
+    (
+        VerbDesc::new("meter_values", "Meter readings of an EVSE")
+            .request(schema::<josev::MeterValuesRequest>)
+            .response(schema::<josev::MeterValuesResponse>),
+        on_meter_values,
+    ),
+    (
+        VerbDesc::new(
+            "stop_charging",
+            "Stop the power delivery, accepted once the contactor is open",
+        )
+        .request(schema::<josev::StopChargingRequest>)
+        .response(schema::<josev::StopChargingResponse>),
+        on_stop_charging,
+    ),
+    (
+        VerbDesc::new("cp_pwm", "Set the CP duty cycle")
+            .request(schema::<josev::CpPwmRequest>)
+            .response(schema::<josev::CpPwmResponse>),
+        on_cp_pwm,
+    ),
+    (
+        VerbDesc::new("change_availability", "OCPP ChangeAvailability of an EVSE")
+            .request(schema::<josev::ChangeAvailabilityRequest>)
+            .response(schema::<josev::ChangeAvailabilityResponse>),
+        on_change_availability,
+    ),
+];
+
+// Verbs called by the MQTT extension and other bindings
+static API_VERBS: [(VerbDesc, VerbHandler); 12] = [
+    (
+        VerbDesc::new(
+            "subscribe",
+            "Receive the events sent to Josev, their current values are pushed at once",
+        ),
+        on_subscribe,
+    ),
+    (
+        VerbDesc::new("unsubscribe", "Stop the events sent to Josev"),
+        on_unsubscribe,
+    ),
+    (
+        VerbDesc::new(
+            "mqtt_config",
+            "Configuration of the MQTT extension, one per topic prefix",
+        )
+        .request(schema::<MqttConfigArgs>),
+        on_mqtt_config,
+    ),
+    (
+        VerbDesc::new("lock_connector", "Lock the connector of an EVSE")
+            .request(schema::<josev::ConnectorLockRequest>)
+            .response(schema::<josev::ConnectorLockResponse>),
+        on_lock_connector,
+    ),
+    (
+        VerbDesc::new(
+            "unlock_connector",
+            "Unlock the connector of an EVSE, rejected while the contactor is closed",
+        )
+        .request(schema::<josev::ConnectorLockRequest>)
+        .response(schema::<josev::ConnectorLockResponse>),
+        on_unlock_connector,
+    ),
+    (
+        VerbDesc::new(
+            "session_state",
+            "Charging session state and its latest transitions",
+        )
+        .response(schema::<SessionStatus>),
+        on_session_state,
+    ),
+    (
+        VerbDesc::new(
+            "transaction",
+            "Current (or last) transaction of an EVSE, as reported by Josev",
+        )
+        .request(schema::<TransactionArgs>)
+        .response(schema::<TransactionRecord>),
+        on_transaction,
+    ),
+    (
+        VerbDesc::new(
+            "availability",
+            "Set (operative or inoperative) or read the availability of the EVSE",
+        )
+        .request(schema::<AvailabilityArgs>)
+        .response(schema::<AvailabilityStatus>),
+        on_availability,
+    ),
+    (
+        VerbDesc::new(
+            "emergency_stop",
+            "Stop at once and open the contactor until rearmed",
+        )
+        .request(schema::<EmergencyStopArgs>),
+        on_emergency_stop,
+    ),
+    (
+        VerbDesc::new(
+            "rearm",
+            "Leave the emergency stop, the contactor open and the cable unplugged",
+        ),
+        on_rearm,
+    ),
+    (
+        VerbDesc::new("service_health", "Status and versions of the Josev services")
+            .response(schema::<ServicesHealth>),
+        on_service_health,
+    ),
+    (
+        VerbDesc::new(
+            "force_cp_state",
+            "Debug: force the CP and contactor states seen by Josev",
+        )
+        .request(schema::<ForceCpStateArgs>),
+        on_force_cp_state,
+    ),
+];
+
+// verbs with a context of their own
+const SUBSCRIBE_JOSEV_VERB: VerbDesc = VerbDesc::new(
+    "subscribe_josev",
+    "Receive (true) or not (false) the forwarded Josev messages",
+)
+.request(schema::<bool>);
+const INFO_VERB: VerbDesc = VerbDesc::new("info", "This description");
+
+// Optional argument of a verb, parsed into its type
+fn verb_args<T: DeserializeOwned + Default>(args: &AfbRqtData) -> Result<T, AfbError> {
+    match args.get::<JsoncObj>(0) {
+        Ok(arg) => serde_json::from_str(&arg.to_string())
+            .or_else(|error| afb_error!(JOSEV_API, "invalid argument {}: {}", arg, error)),
+        Err(_) => Ok(T::default()),
+    }
+}
+
+// Handlers of the JOSEV_MESSAGES
+static JOSEV_HANDLERS: [(&str, MessageHandler); 6] = [
+    ("iso15118_state_info", mqtt_event_cb),
+    ("iso15118_charge_limit", charge_limit_evt_cb),
+    ("hlc_charging", on_hlc_charging),
+    ("transaction_status", on_transaction_status),
+    ("slac_status", on_slac_status),
+    ("service_status", on_service_status),
+];
+
+impl AfbApiControls for SharedContext {
+    // the API is created and ready. At this level user may subcall api(s) declare as dependencies
+    fn start(&mut self, api: &AfbApi) -> Result<(), AfbError> {
+        println!("== JOSEV binding starting");
+        let config = &self.config;
+
+        // Subscribe to IEC events
+        AfbSubCall::call_sync(api, config.charge_api, "subscribe", true)?;
+
+        // Subscribe to CP measurements
+        if let Some(iec_api) = config.iec_api {
+            AfbSubCall::call_sync(api, iec_api, "subscribe", true)?;
+        }
+
+        // Reset authentication
+        if let Err(_err) = AfbSubCall::call_sync(api, config.auth_api, "logout", 0) {
+            afb_log_msg!(
+                Notice,
+                api.get_apiv4(),
+                "**logout failed**, probably already logged out"
+            );
+        }
+
+        // Subscribe to Josev MQTT messages
+        let subscribed_messages = JsoncObj::array();
+        for message in &config.josev_messages {
+            subscribed_messages.append(*message)?;
+        }
+        AfbSubCall::call_sync(api, "from_mqtt", "subscribe_events", subscribed_messages)?;
+
+        // Restarted in the middle of a session ?
+        if let Err(error) = recover_session(api, self) {
+            afb_log_msg!(Warning, api.get_apiv4(), "Session not recovered: {}", error);
+        }
+
+        // Watch Josev liveness, from now on
+        if let Some(watchdog) = config.watchdog {
+            self.shared.write().unwrap().josev_alive();
+            AfbTimer::new("josev-watchdog")
+                .set_period(watchdog.period())
+                .set_callback(watchdog_timer_cb)
+                .set_context(self.clone())
+                .start()?;
+        }
+        Ok(())
+    }
+
+    // the binder is stopping: leave the station in a safe state and tell Josev.
+    // The event loop is going away, hence the synchronous subcalls.
+    fn exit(&mut self, api: &AfbApi, code: i32) -> i32 {
+        println!("== JOSEV binding stopping");
+        let config = &self.config;
+
+        // Open the contactor
+        let opened = AfbSubCall::call_sync(api, config.charge_api, "remote_power", false);
+        if let Err(error) = &opened {
+            afb_log_msg!(Error, api.get_apiv4(), "Shutdown: contactor not opened: {}", error);
+        }
+
+        {
+            let mut ctx = self.shared.write().unwrap();
+            ctx.shutdown = true;
+            if opened.is_ok() {
+                session_saved(api.get_apiv4(), ctx.session.set_contactor_closed(false));
+                ctx.push_contactor_status(false);
+            }
+            ctx.cp_status_event
+                .push(ctx.cp_status_update(josev::ControlPilotState::A1));
+            ctx.apply_status_and_limits();
+            ctx.cs_status_and_limits_event
+                .push(ctx.cs_status_and_limits.clone());
+        }
+
+        if let Err(error) = AfbSubCall::call_sync(api, config.auth_api, "logout", 0) {
+            afb_log_msg!(Notice, api.get_apiv4(), "Shutdown: logout failed: {}", error);
+        }
+
+        let subscribed_messages = JsoncObj::array();
+        for message in &config.josev_messages {
+            let _ = subscribed_messages.append(*message);
+        }
+        if let Err(error) =
+            AfbSubCall::call_sync(api, "from_mqtt", "unsubscribe_events", subscribed_messages)
+        {
+            afb_log_msg!(Notice, api.get_apiv4(), "Shutdown: unsubscribe failed: {}", error);
+        }
+
+        code
+    }
+
+    // mandatory unsed declaration
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
 fn charge_event_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
     let ctx: &mut SharedContext = ctx.get_mut::<SharedContext>()?;
 
     let msg: &ChargingMsg = args.get::<&ChargingMsg>(0)?;
     afb_log_msg!(Debug, evt.get_apiv4(), "Charge event received {:?}", msg);
 
-    {
+    let iec_api = ctx.config.iec_api;
+    let mut emergency = None;
+    let (unlock, opened) = {
         let mut b1_b2_transition = false;
+        let mut cp_changed = false;
         let mut ctx = ctx.shared.write().unwrap();
         match msg {
             ChargingMsg::Plugged(plugged) => {
+                let cp_state = plug_cp_state(*plugged);
+                if let Some(cp_state) = cp_state {
+                    if matches!(cp_state, josev::ControlPilotState::B2)
+                        && matches!(ctx.session.cp_state(), josev::ControlPilotState::A1)
+                    {
+                        b1_b2_transition = true;
+                    }
+                    session_saved(evt.get_apiv4(), ctx.session.set_cp_state(cp_state));
+                }
                 match *plugged {
                     PlugState::PlugIn => {
-                        if matches!(ctx.charging_state, josev::ControlPilotState::A1) {
-                            b1_b2_transition = true;
+                        if ctx.pp_fault() {
+                            // the cable rating came before the plug event
+                            afb_log_msg!(
+                                Error,
+                                evt.get_apiv4(),
+                                "Invalid proximity pilot, cable rejected"
+                            );
+                            ctx.enter(
+                                evt.get_apiv4(),
+                                SessionState::Fault,
+                                "invalid proximity pilot",
+                            );
+                        } else if ctx.session.state() != SessionState::Fault {
+                            // a fault (invalid cable, lock failure) outlives the plug events
+                            ctx.enter(evt.get_apiv4(), SessionState::Plugged, "plug in");
                         }
-                        ctx.charging_state = josev::ControlPilotState::B2;
                     }
                     PlugState::Lock => {
-                        ctx.charging_state = josev::ControlPilotState::C2;
+                        ctx.connector_locked = true;
+                    }
+                    PlugState::Error => {
+                        ctx.enter(evt.get_apiv4(), SessionState::Fault, "plug error");
                     }
                     _ => {
-                        ctx.charging_state = josev::ControlPilotState::A1;
+                        // unplugged: a paused session cannot be resumed anymore
+                        ctx.enter(evt.get_apiv4(), SessionState::Idle, "unplugged");
+                        // and the session limits do not apply anymore
+                        ctx.auth_max_current = None;
+                        ctx.cable_max_current = None;
+                        // nor the cable lock
+                        ctx.connector_locked = false;
+                        ctx.unlock_pending = false;
+                        ctx.lock_fault = false;
                     }
                 }
 
-                if ctx.forced_charging_state.is_none() {
+                if ctx.apply_status_and_limits() {
+                    ctx.cs_status_and_limits_event
+                        .push(ctx.cs_status_and_limits.clone());
+                }
+
+                if cp_state.is_some() && !ctx.cp_overridden() {
                     if b1_b2_transition {
                         // Moving from A1 to B2 is sometimes too extreme,
                         // move first to B1 before moving to B2
-                        ctx.cp_status_event.push(josev::CpStatusUpdate {
-                            evse_id: ctx.cs_parameters.parameters[0].evse_id.clone(),
-                            connector_id: ctx.cs_parameters.parameters[0].connectors[0].id,
-                            state: josev::ControlPilotState::B1,
-                            max_voltage: None,
-                            min_voltage: None,
-                            duty_cycle: None,
-                        });
+                        ctx.cp_status_event
+                            .push(ctx.cp_status_update(josev::ControlPilotState::B1));
                     }
-                    ctx.cp_status_event.push(josev::CpStatusUpdate {
-                        evse_id: ctx.cs_parameters.parameters[0].evse_id.clone(),
-                        connector_id: ctx.cs_parameters.parameters[0].connectors[0].id,
-                        state: ctx.charging_state,
-                        max_voltage: None,
-                        min_voltage: None,
-                        duty_cycle: None,
-                    });
+                    ctx.cp_status_event
+                        .push(ctx.cp_status_update(ctx.session.cp_state()));
                 }
             }
             ChargingMsg::Power(power_state) => {
                 match *power_state {
                     PowerRequest::Start | PowerRequest::Charging(_) => {
-                        ctx.contactor_closed = true;
+                        session_saved(evt.get_apiv4(), ctx.session.set_contactor_closed(true));
+                        if ctx.session.is_paused() {
+                            // the EV resumes the paused session
+                            if matches!(ctx.session.cp_state(), josev::ControlPilotState::B2) {
+                                session_saved(
+                                    evt.get_apiv4(),
+                                    ctx.session.set_cp_state(josev::ControlPilotState::C2),
+                                );
+                                cp_changed = true;
+                            }
+                        }
+                        ctx.enter(evt.get_apiv4(), SessionState::Charging, "power on");
                     }
                     PowerRequest::Stop(_) => {
-                        ctx.contactor_closed = false;
+                        session_saved(evt.get_apiv4(), ctx.session.set_contactor_closed(false));
+                    }
+                    PowerRequest::Idle => {
+                        // The EV went back to state B while charging: this is a pause,
+                        // the session (and its authorization) is still alive
+                        if ctx.session.contactor_closed()
+                            || matches!(ctx.session.cp_state(), josev::ControlPilotState::C2)
+                        {
+                            session_saved(
+                                evt.get_apiv4(),
+                                ctx.session.set_cp_state(josev::ControlPilotState::B2),
+                            );
+                            cp_changed = true;
+                            ctx.enter(evt.get_apiv4(), SessionState::Paused, "ev back to state B");
+                        }
+                        session_saved(evt.get_apiv4(), ctx.session.set_contactor_closed(false));
                     }
-                    _ => {}
+                }
+                if !ctx.session.contactor_closed() && ctx.session.state() == SessionState::Stopping {
+                    ctx.enter(evt.get_apiv4(), SessionState::Plugged, "contactor open");
                 }
                 if ctx.forced_contactor_closed.is_none() {
-                    ctx.contactor_status_event
-                        .push(josev::CsContactorStatusUpdate {
-                            evse_id: ctx.cs_parameters.parameters[0].evse_id.clone(),
-                            status: {
-                                if ctx.contactor_closed {
-                                    josev::CsContactorStatusResponseStatus::Closed
-                                } else {
-                                    josev::CsContactorStatusResponseStatus::Opened
-                                }
-                            },
-                            info: None,
-                        });
+                    let closed = ctx.session.contactor_closed();
+                    ctx.push_contactor_status(closed);
                 }
+                ctx.report_cp_fault();
+                if cp_changed && !ctx.cp_overridden() {
+                    ctx.cp_status_event
+                        .push(ctx.cp_status_update(ctx.session.cp_state()));
+                }
+            }
+            ChargingMsg::EmergencyStop { reason } => {
+                emergency = Some(reason.clone());
             }
             _ => {}
         }
+
+        (
+            // the session is over and the contactor is now open
+            ctx.unlock_pending && !ctx.session.contactor_closed(),
+            // without relay feedback, trust the power state of the charge manager
+            iec_api.is_none()
+                && !ctx.session.contactor_closed()
+                && !ctx.open_waiters.is_empty(),
+        )
+    };
+
+    if opened {
+        reply_open_waiters(ctx, None, josev::MessageStatus::Accepted);
+    }
+
+    if let Some(reason) = emergency {
+        emergency_stop(evt.get_apiv4(), ctx, reason)?;
+    }
+
+    if unlock {
+        remote_lock(evt.get_apiv4(), ctx, false, None)?;
     }
 
     Ok(())
 }
 
 //
-// Verb dedicated to the MQTT extension so that an MQTT update message
-// is sent when we push to a set of events
-fn on_subscribe(
-    request: &AfbRequest,
-    _args: &AfbRqtData,
-    ctx: &AfbCtxData,
-) -> Result<(), AfbError> {
-    let ctx = ctx.get_ref::<SharedContext>()?;
+// After a restart, take back the saved session when the charge manager says
+// the EV is still plugged in, the relay state of the charge manager wins
+// (and the session state follows it).
+// Josev is then told the actual CP and contactor states.
+fn recover_session(api: &AfbApi, ctx: &SharedContext) -> Result<(), AfbError> {
+    let reply = AfbSubCall::call_sync(api, ctx.config.charge_api, "state", ChargingAction::READ)?;
+    let state: &ChargingState = reply.get_onsuccess::<&ChargingState>(0)?;
+    let plugged = matches!(state.plugged, PlugState::PlugIn | PlugState::Lock);
+    let closed = matches!(state.power, PowerRequest::Start | PowerRequest::Charging(_));
+
+    let mut ctx = ctx.shared.write().unwrap();
+    let saved = match ctx.session.load() {
+        Ok(saved) => saved.filter(|saved| saved.state != SessionState::Idle),
+        Err(error) => {
+            afb_log_msg!(Warning, api.get_apiv4(), "{}", error);
+            None
+        }
+    };
 
-    {
-        let ctx = ctx.shared.read().unwrap();
-        ctx.cp_status_event.subscribe(request)?;
-        ctx.authorization_event.subscribe(request)?;
-        ctx.contactor_status_event.subscribe(request)?;
-        ctx.cs_status_and_limits_event.subscribe(request)?;
+    if plugged {
+        let mut snapshot = saved.unwrap_or(SessionSnapshot {
+            state: if closed {
+                SessionState::Charging
+            } else {
+                SessionState::Plugged
+            },
+            cp_state: josev::ControlPilotState::B2,
+            contactor_closed: closed,
+            iso_state: None,
+            payment_option: state.payment,
+            authorized_token: None,
+        });
+        snapshot.reconcile(closed);
+        if snapshot.iso_state.is_none() {
+            snapshot.iso_state = match state.iso {
+                IsoState::Iso2 | IsoState::Iso20 | IsoState::Iso20Discharge => Some(state.iso),
+                _ => None,
+            };
+        }
+        afb_log_msg!(
+            Notice,
+            api.get_apiv4(),
+            "Session recovered in state {:?}, contactor {}",
+            snapshot.state,
+            if closed { "closed" } else { "open" }
+        );
+        ctx.session.restore(snapshot, "recovered after restart");
+        ctx.connector_locked = closed || matches!(state.plugged, PlugState::Lock);
     }
 
-    request.reply(AFB_NO_DATA, 0);
+    // a session saved before an unplug is over, forget it
+    if let Err(error) = ctx.session.save() {
+        afb_log_msg!(Warning, api.get_apiv4(), "{}", error);
+    }
+
+    ctx.cp_status_event.push(ctx.cp_status_update(ctx.cp_state()));
+    let closed = ctx.session.contactor_closed();
+    ctx.push_contactor_status(closed);
     Ok(())
 }
 
-fn mqtt_event_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+//
+// Ask the charge manager to close or open the contactor,
+// the connector is locked before the contactor is closed
+fn remote_power(api: AfbApiV4, ctx: &SharedContext, power: bool) -> Result<(), AfbError> {
+    if power {
+        let locked = {
+            let ctx = ctx.shared.read().unwrap();
+            if ctx.pp_fault() {
+                return afb_error!(
+                    JOSEV_API,
+                    "contactor not closed, invalid proximity pilot"
+                );
+            }
+            if let Some(reason) = &ctx.emergency {
+                return afb_error!(JOSEV_API, "contactor not closed, emergency stop: {}", reason);
+            }
+            if ctx.refuses_sessions() {
+                return afb_error!(JOSEV_API, "contactor not closed, EVSE is not available");
+            }
+            ctx.connector_locked
+        };
+        if !locked {
+            let charge_api = ctx.config.charge_api;
+            // never retried, a late retry could undo a later command
+            let policy = ctx.config.subcalls.charge_api.once();
+            return remote_lock(
+                api,
+                ctx,
+                true,
+                Some(Box::new(move |api, locked| {
+                    locked?;
+                    subcall::send(api, charge_api, "remote_power", power, policy)
+                })),
+            );
+        }
+    }
+    subcall::send(
+        api,
+        ctx.config.charge_api,
+        "remote_power",
+        power,
+        ctx.config.subcalls.charge_api.once(),
+    )
+}
+
+//
+// Continuation of a lock request, called with the lock status
+type LockDone = Box<dyn FnOnce(AfbApiV4, Result<(), AfbError>) -> Result<(), AfbError>>;
+
+//
+// Ask the charge manager to lock or unlock the connector,
+// a lock failure is reported to Josev as an EVSE error
+fn remote_lock(
+    api: AfbApiV4,
+    ctx: &SharedContext,
+    lock: bool,
+    done: Option<LockDone>,
+) -> Result<(), AfbError> {
+    let shared = ctx.clone();
+    subcall::call_async(
+        api,
+        ctx.config.charge_api,
+        "remote_lock",
+        lock,
+        ctx.config.subcalls.charge_api.once(),
+        Some(Box::new(move |api, reply| {
+            let status = reply.map(|_| ());
+            on_lock_status(api, &shared, lock, &status);
+            match done {
+                Some(done) => done(api, status),
+                None => status,
+            }
+        })),
+    )
+}
+
+fn on_lock_status(api: AfbApiV4, ctx: &SharedContext, lock: bool, status: &Result<(), AfbError>) {
+    let mut ctx = ctx.shared.write().unwrap();
+    match status {
+        Ok(_) => {
+            ctx.connector_locked = lock;
+            if !lock {
+                ctx.unlock_pending = false;
+            }
+        }
+        Err(error) if lock => {
+            afb_log_msg!(Error, api, "Connector lock failed: {}", error);
+            ctx.lock_fault = true;
+            ctx.enter(api, SessionState::Fault, "connector lock failed");
+            let mut update = ctx.contactor_status_update(ctx.session.contactor_closed());
+            update.status = josev::CsContactorStatusResponseStatus::Error;
+            update.info = Some("connector lock failed".to_string());
+            ctx.contactor_status_event.push(update);
+            ctx.contactor_pushed = None;
+            if ctx.apply_status_and_limits() {
+                ctx.cs_status_and_limits_event
+                    .push(ctx.cs_status_and_limits.clone());
+            }
+        }
+        Err(error) => {
+            afb_log_msg!(Error, api, "Connector unlock failed: {}", error);
+        }
+    }
+}
+
+//
+// Emergency stop: Josev is told the EVSE is in emergency shutdown,
+// the contactor is opened and the CP is forced to F until re-armed
+fn emergency_stop(api: AfbApiV4, ctx: &SharedContext, reason: String) -> Result<(), AfbError> {
+    {
+        let mut ctx = ctx.shared.write().unwrap();
+        if ctx.emergency.is_some() {
+            return Ok(());
+        }
+        afb_log_msg!(Critical, api, "Emergency stop: {}", reason);
+        ctx.emergency = Some(reason);
+        ctx.enter(api, SessionState::Fault, "emergency stop");
+        if ctx.apply_status_and_limits() {
+            ctx.cs_status_and_limits_event
+                .push(ctx.cs_status_and_limits.clone());
+        }
+    }
+    command_cp_fault(api, ctx, true)?;
+    remote_power(api, ctx, false)
+}
+
+//
+// Leave the emergency stop, only once the contactor is open and the cable unplugged
+fn emergency_rearm(api: AfbApiV4, ctx: &SharedContext) -> Result<(), AfbError> {
+    {
+        let mut ctx = ctx.shared.write().unwrap();
+        if ctx.emergency.is_none() {
+            return Ok(());
+        }
+        if ctx.session.contactor_closed() {
+            return afb_error!(JOSEV_API, "cannot re-arm, contactor is closed");
+        }
+        if !matches!(ctx.session.cp_state(), josev::ControlPilotState::A1) {
+            return afb_error!(JOSEV_API, "cannot re-arm, cable is plugged");
+        }
+
+        afb_log_msg!(Notice, api, "EVSE re-armed after emergency stop");
+        ctx.emergency = None;
+        ctx.enter(api, SessionState::Idle, "re-armed");
+        if ctx.apply_status_and_limits() {
+            ctx.cs_status_and_limits_event
+                .push(ctx.cs_status_and_limits.clone());
+        }
+    }
+    command_cp_fault(api, ctx, false)
+}
+
+//
+// Hold the CP in state F, or release it, through the IEC api.
+// Josev is only told state F once the IEC api did it, or without
+// CP fault verb, once the contactor is confirmed open.
+fn command_cp_fault(api: AfbApiV4, ctx: &SharedContext, fault: bool) -> Result<(), AfbError> {
+    let (Some(iec_api), Some(verb)) = (ctx.config.iec_api, ctx.config.cp_fault_verb) else {
+        let mut ctx = ctx.shared.write().unwrap();
+        ctx.cp_fault_pending = fault;
+        if fault {
+            ctx.report_cp_fault();
+        } else if ctx.cp_fault {
+            ctx.cp_fault = false;
+            ctx.cp_status_event.push(ctx.cp_status_update(ctx.cp_state()));
+        }
+        return Ok(());
+    };
+    let shared = ctx.clone();
+    subcall::call_async(
+        api,
+        iec_api,
+        verb,
+        fault,
+        SubcallPolicy::default(),
+        Some(Box::new(move |api, reply| {
+            if let Err(error) = reply {
+                afb_log_msg!(Critical, api, "cannot set CP fault {}: {}", fault, error);
+                return Ok(());
+            }
+            let mut ctx = shared.shared.write().unwrap();
+            ctx.cp_fault = fault;
+            ctx.cp_status_event.push(ctx.cp_status_update(ctx.cp_state()));
+            Ok(())
+        })),
+    )
+}
+
+//
+// Set the EVSE operative or inoperative. When set inoperative, an ongoing session
+// is either stopped or allowed to finish, depending on the configuration
+fn set_availability(
+    api: AfbApiV4,
+    ctx: &SharedContext,
+    operational_status: josev::OperationalStatus,
+) -> Result<josev::ChangeAvailabilityStatus, AfbError> {
+    let finish_session = ctx.config.finish_session_when_inoperative;
+    let (status, open) = {
+        let mut ctx = ctx.shared.write().unwrap();
+        ctx.availability = match operational_status {
+            josev::OperationalStatus::Operative => Availability::Operative,
+            josev::OperationalStatus::Inoperative
+                if ctx.session.is_active() && finish_session =>
+            {
+                Availability::Scheduled
+            }
+            josev::OperationalStatus::Inoperative => Availability::Inoperative,
+        };
+        afb_log_msg!(Notice, api, "EVSE availability {:?}", ctx.availability);
+
+        if ctx.apply_status_and_limits() {
+            ctx.cs_status_and_limits_event
+                .push(ctx.cs_status_and_limits.clone());
+        }
+
+        let status = if ctx.availability == Availability::Scheduled {
+            josev::ChangeAvailabilityStatus::Scheduled
+        } else {
+            josev::ChangeAvailabilityStatus::Accepted
+        };
+        (
+            status,
+            ctx.availability == Availability::Inoperative && ctx.session.contactor_closed(),
+        )
+    };
+
+    if open {
+        remote_power(api, ctx, false)?;
+    }
+    Ok(status)
+}
+
+//
+// At the end of a session, unlock the connector as soon as the contactor is open
+fn unlock_at_session_end(api: AfbApiV4, ctx: &SharedContext) -> Result<(), AfbError> {
+    let unlock_now = {
+        let mut ctx = ctx.shared.write().unwrap();
+        if ctx.session.contactor_closed() {
+            ctx.unlock_pending = true;
+            false
+        } else {
+            ctx.connector_locked
+        }
+    };
+    if unlock_now {
+        remote_lock(api, ctx, false, None)?;
+    }
+    Ok(())
+}
+
+//
+// CP measurements coming from the IEC api, pushed to Josev
+// only when they move by more than the configured deadband
+fn iec_event_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    let config = &ctx.config;
+
+    let msg: &Iec6185Msg = args.get::<&Iec6185Msg>(0)?;
+    afb_log_msg!(Debug, evt.get_apiv4(), "IEC event received {:?}", msg);
+
+    match msg {
+        Iec6185Msg::CpMeasure(measure) => {
+            let mut ctx = ctx.shared.write().unwrap();
+            if config.cp_deadband.exceeded(&ctx.cp_measure, measure) {
+                ctx.cp_measure = Some(*measure);
+                ctx.cp_status_event.push(ctx.cp_status_update(ctx.cp_state()));
+            }
+        }
+        Iec6185Msg::RelayOn(closed) => {
+            let opened = {
+                let mut ctx = ctx.shared.write().unwrap();
+                ctx.relay_closed = Some(*closed);
+                if ctx.forced_contactor_closed.is_none() {
+                    ctx.push_contactor_status(*closed);
+                }
+                ctx.report_cp_fault();
+                !*closed && !ctx.open_waiters.is_empty()
+            };
+            if opened {
+                reply_open_waiters(ctx, None, josev::MessageStatus::Accepted);
+            }
+        }
+        Iec6185Msg::CableImax(imax) => {
+            let pp_fault = {
+                let mut ctx = ctx.shared.write().unwrap();
+                ctx.cable_max_current = Some(*imax as f32);
+                if ctx.pp_fault() {
+                    ctx.enter(evt.get_apiv4(), SessionState::Fault, "invalid proximity pilot");
+                }
+                if ctx.apply_status_and_limits() {
+                    // Make Josev aware of the new current limitation
+                    ctx.cs_status_and_limits_event
+                        .push(ctx.cs_status_and_limits.clone());
+                }
+                ctx.pp_fault()
+            };
+            if pp_fault {
+                afb_log_msg!(Error, evt.get_apiv4(), "Invalid proximity pilot, cable rejected");
+                remote_power(evt.get_apiv4(), ctx, false)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+//
+// Verb dedicated to the MQTT extension so that an MQTT update message
+// is sent when we push to a set of events.
+// The current value of each event is pushed at once.
+fn on_subscribe(
+    request: &AfbRequest,
+    _args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<SharedContext>()?;
+
+    {
+        let ctx = ctx.shared.read().unwrap();
+        ctx.cp_status_event.subscribe(request)?;
+        ctx.authorization_event.subscribe(request)?;
+        ctx.contactor_status_event.subscribe(request)?;
+        ctx.cs_status_and_limits_event.subscribe(request)?;
+
+        // A Josev connecting late gets the current state right away
+        ctx.resync();
+        if ctx.session.is_active() {
+            if let Some(token) = ctx.session.authorized_token() {
+                ctx.authorization_event.push(josev::AuthorizationUpdate {
+                    evse_id: ctx.cs_parameters.parameters[0].evse_id.clone(),
+                    token_type: josev::AuthorizationTokenType::ISO14443,
+                    status: josev::AuthorizationStatus::Accepted,
+                    id_token: Some(token.to_string()),
+                });
+            }
+        }
+    }
+
+    request.reply(AFB_NO_DATA, 0);
+    Ok(())
+}
+
+fn on_unsubscribe(
+    request: &AfbRequest,
+    _args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<SharedContext>()?;
+
+    {
+        let ctx = ctx.shared.read().unwrap();
+        ctx.cp_status_event.unsubscribe(request)?;
+        ctx.authorization_event.unsubscribe(request)?;
+        ctx.contactor_status_event.unsubscribe(request)?;
+        ctx.cs_status_and_limits_event.unsubscribe(request)?;
+    }
+
+    request.reply(AFB_NO_DATA, 0);
+    Ok(())
+}
+
+//
+// Configuration of afb-mqtt-ext matching the binding, one per topic prefix.
+// Optional arguments: "prefixes" (one Josev per EVSE), "host" and "port" of the broker.
+fn on_mqtt_config(
+    request: &AfbRequest,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    // ports above 65535 do not parse
+    let args = verb_args::<MqttConfigArgs>(args)?;
+    let broker = Broker {
+        host: args.host.as_deref().unwrap_or("localhost"),
+        port: match args.port {
+            Some(0) => return afb_error!(JOSEV_API, "invalid MQTT broker port 0"),
+            Some(port) => port,
+            None => 1883,
+        },
+    };
+    let mut prefixes: Vec<Option<&str>> = args
+        .prefixes
+        .iter()
+        .map(|prefix| Some(prefix.as_str()))
+        .collect();
+    if prefixes.is_empty() {
+        prefixes.push(None);
+    }
+
+    let ping = ctx
+        .config
+        .watchdog
+        .and_then(|watchdog| watchdog.ping)
+        .filter(|ping| !TO_JOSEV.contains(ping));
+    let to_josev: Vec<&str> = TO_JOSEV.into_iter().chain(ping).collect();
+    let mapping = MqttMapping {
+        api: JOSEV_API,
+        subscribe_verb: "subscribe",
+        requests: &JOSEV_REQUESTS,
+        to_josev: &to_josev,
+        events: &JOSEV_EVENTS,
+        messages: &ctx.config.josev_messages,
+    };
+
+    let configs = JsoncObj::array();
+    for prefix in prefixes {
+        let config = JsoncObj::new();
+        if let Some(prefix) = prefix {
+            config.add("prefix", prefix)?;
+        }
+        config.add("config", generate(&mapping, &broker, prefix).as_str())?;
+        configs.append(config)?;
+    }
+
+    request.reply(configs, 0);
+    Ok(())
+}
+
+fn mqtt_event_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
     let msg = args.get::<JsoncObj>(0)?;
     let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
     let config = &ctx.config;
 
     let evse_id = msg.get::<&'static str>("evse_id")?;
@@ -230,27 +1434,44 @@ fn mqtt_event_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Resu
                         }
                     };
 
-                    {
+                    let negotiating = {
                         // store the iso state in the context
                         let mut ctx = ctx.shared.write().unwrap();
-                        ctx.iso_state = Some(iso_state);
+                        let negotiating = ctx.enter(
+                            evt.get_apiv4(),
+                            SessionState::HlcNegotiation,
+                            "supported app protocol",
+                        );
+                        if negotiating {
+                            session_saved(
+                                evt.get_apiv4(),
+                                ctx.session.set_iso_state(Some(iso_state)),
+                            );
+                        }
+                        negotiating
+                    };
+                    if !negotiating {
+                        return Ok(());
                     }
-                    
-                    AfbSubCall::call_sync(
+
+                    subcall::send(
                         evt.get_apiv4(),
                         config.charge_api,
                         "iso-state",
                         ChargingMsg::Iso(iso_state),
+                        config.subcalls.charge_api,
                     )?;
                 }
             }
         } else if session_status == "Authorization" {
             if let Ok(info) = msg.get::<JsoncObj>("info") {
                 // The keyword "selectedPaymentOption" in python version
-                // is different in the rust version ("selected_payment_option")
-                // So this part will only work in python-iso15118
-                if let Ok(selected_payment_option) =
-                    info.get::<&'static str>("selectedPaymentOption")
+                // is different in the rust version ("selected_payment_option"),
+                // the one of the running stack is tried first
+                let dialect = ctx.shared.read().unwrap().dialect;
+                if let Some(selected_payment_option) = compat::payment_option_keys(dialect)
+                    .iter()
+                    .find_map(|key| info.get::<&'static str>(key).ok())
                 {
                     let payment_option = match selected_payment_option.to_lowercase().as_str() {
                         "eim" => PaymentOption::Eim,
@@ -267,204 +1488,360 @@ fn mqtt_event_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Resu
                     {
                         // store the payment mode in the context
                         let mut ctx = ctx.shared.write().unwrap();
-                        ctx.payment_option = Some(payment_option);
+                        session_saved(
+                            evt.get_apiv4(),
+                            ctx.session.set_payment_option(Some(payment_option)),
+                        );
                     }
 
-                    AfbSubCall::call_sync(
+                    subcall::send(
                         evt.get_apiv4(),
                         config.charge_api,
                         "payment-option",
                         ChargingMsg::Payment(payment_option),
+                        config.subcalls.charge_api,
                     )?;
                 }
             }
 
-            // In EIM, we extract from the smart card whether we need OCPP to authorize the user or not
-            // In PnC, we do nothing, Josev will forward the authorization request to the OCPP backend
-            let payment_option = {
+            // A paused session being resumed keeps its authorization,
+            // no new login (and no new transaction) is required
+            {
                 let ctx = ctx.shared.read().unwrap();
-                ctx.payment_option
-            };
-            if let Some(PaymentOption::Eim) = payment_option {
-                // Ask for authorization
-                let auth_reply =
-                    AfbSubCall::call_sync(evt.get_apiv4(), config.auth_api, "login", false)?;
-                let auth_state: &AuthState = auth_reply.get_onsuccess::<&AuthState>(0)?;
-                if matches!(auth_state.auth, AuthMsg::Done) {
-                    // Limit max current to the one stored on the card
-                    {
-                        let mut ctx = ctx.shared.write().unwrap();
-                        if let Some(ac_limits) = &mut ctx.cs_status_and_limits.evses[0].ac {
-                            ac_limits.max_current.l1 = auth_state.imax as f32;
-                            ac_limits.max_current.l2 = auth_state.imax as f32;
-                            ac_limits.max_current.l3 = auth_state.imax as f32;
-                        }
-
-                        // Make Josev aware of the new current limitation
-                        ctx.cs_status_and_limits_event
-                            .push(ctx.cs_status_and_limits.clone());
-                    };
-
-                    if auth_state.ocpp_check {
-                        // We ask josev for an authorization with this token.
-                        // It will be forwarded to OCPP
-                        AfbSubCall::call_sync(
-                            evt.get_apiv4(),
-                            "to_mqtt",
-                            "authorization",
-                            josev::AuthorizationRequest {
-                                evse_id: Some(evse_id.to_string()),
-                                id_token: Some(auth_state.tagid.clone()),
-                                token_type: josev::AuthorizationTokenType::ISO14443,
-                            },
-                        )?;
-                    } else {
-                        let ctx = ctx.shared.read().unwrap();
-                        // Otherwise, no OCPP is involved and we accept the authorization
-                        // by issuing an "update" Authorization message
+                if ctx.session.is_paused() {
+                    if let Some(token) = ctx.session.authorized_token() {
                         ctx.authorization_event.push(josev::AuthorizationUpdate {
                             evse_id: evse_id.to_string(),
                             token_type: josev::AuthorizationTokenType::ISO14443,
                             status: josev::AuthorizationStatus::Accepted,
-                            id_token: Some(auth_state.tagid.clone()),
+                            id_token: Some(token.to_string()),
                         });
+                        return Ok(());
                     }
                 }
             }
+
+            // An EVSE out of service does not authorize anybody
+            {
+                let ctx = ctx.shared.read().unwrap();
+                if ctx.refuses_sessions() {
+                    ctx.authorization_event.push(josev::AuthorizationUpdate {
+                        evse_id: evse_id.to_string(),
+                        token_type: josev::AuthorizationTokenType::ISO14443,
+                        status: josev::AuthorizationStatus::Rejected,
+                        id_token: None,
+                    });
+                    return Ok(());
+                }
+            }
+
+            // In EIM, we extract from the smart card whether we need OCPP to authorize the user or not
+            // In PnC, we do nothing, Josev will forward the authorization request to the OCPP backend
+            let payment_option = {
+                let ctx = ctx.shared.read().unwrap();
+                ctx.session.payment_option()
+            };
+            if let Some(PaymentOption::Eim) = payment_option {
+                // Ask for authorization, the session goes on when the reply arrives
+                let shared = ctx.clone();
+                let evse_id = evse_id.to_string();
+                subcall::call_async(
+                    evt.get_apiv4(),
+                    config.auth_api,
+                    "login",
+                    false,
+                    // a second login would open another session
+                    config.subcalls.auth_api.once(),
+                    Some(Box::new(move |api, reply| {
+                        let auth_state: &AuthState = reply?.get_onsuccess::<&AuthState>(0)?;
+                        on_eim_login(api, &shared, &evse_id, auth_state)
+                    })),
+                )?;
+            }
         } else if session_status == "ScheduleExchange" {
             // In iso-20, the contactor must be closed before PowerDeliveryReq
-            // ScheduleExchange is the state just before PowerDelivery
-            AfbSubCall::call_sync(evt.get_apiv4(), ctx.config.charge_api, "remote_power", true)?;
+            let iso_state = {
+                let ctx = ctx.shared.read().unwrap();
+                ctx.session.iso_state()
+            };
+            if config
+                .contactor_policy
+                .should_close(iso_state, ContactorTrigger::ScheduleExchange)
+            {
+                remote_power(evt.get_apiv4(), ctx, true)?;
+            }
         } else if session_status == "SessionStop" {
-            // Open the contactor
-            AfbSubCall::call_sync(
-                evt.get_apiv4(),
-                ctx.config.charge_api,
-                "remote_power",
-                false,
-            )?;
-        }
-    }
-
-    Ok(())
-}
-
-fn on_hlc_charging(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
-    let msg = args.get::<JsoncObj>(0)?;
-    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
-    let config = &ctx.config;
-
-    // Always authorize the session
-    if let Ok(evse_id) = msg.get::<&'static str>("evse_id") {
-        if let Ok(status) = msg.get::<bool>("status") {
-            if config.evse_id != evse_id {
-                // ignore messages of other EVSE IDs
+            let dialect = ctx.shared.read().unwrap().dialect;
+            let paused = session_stop_is_pause(&msg, dialect);
+            let (stopped, iso_state) = {
+                // ISO 15118-20 pause: the EV will come back with the same session
+                let mut ctx = ctx.shared.write().unwrap();
+                let stopped = if paused {
+                    ctx.enter(evt.get_apiv4(), SessionState::Paused, "session stop (pause)")
+                } else {
+                    ctx.enter(evt.get_apiv4(), SessionState::Stopping, "session stop")
+                };
+                (stopped, ctx.session.iso_state())
+            };
+            if !stopped {
                 return Ok(());
             }
-            // Close the contactor
-            AfbSubCall::call_sync(evt.get_apiv4(), config.charge_api, "remote_power", status)?;
 
+            // Open the contactor
+            if config
+                .contactor_policy
+                .should_open(iso_state, ContactorTrigger::SessionStop)
             {
-                let ctx = ctx.shared.write().unwrap();
-                ctx.cp_status_event.push(josev::CpStatusUpdate {
-                    evse_id: ctx.cs_parameters.parameters[0].evse_id.clone(),
-                    connector_id: ctx.cs_parameters.parameters[0].connectors[0].id,
-                    state: {
-                        if let Some(forced) = ctx.forced_charging_state {
-                            forced
-                        } else {
-                            ctx.charging_state
-                        }
-                    },
-                    max_voltage: None,
-                    min_voltage: None,
-                    duty_cycle: None,
-                });
+                remote_power(evt.get_apiv4(), ctx, false)?;
+            }
+
+            if !paused {
+                unlock_at_session_end(evt.get_apiv4(), ctx)?;
             }
         }
     }
+
     Ok(())
 }
 
-fn on_transaction_status(
-    evt: &AfbEventMsg,
-    args: &AfbRqtData,
-    ctx: &AfbCtxData,
+fn on_eim_login(
+    api: AfbApiV4,
+    ctx: &SharedContext,
+    evse_id: &str,
+    auth_state: &AuthState,
 ) -> Result<(), AfbError> {
-    let msg = args.get::<JsoncObj>(0)?;
-    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
-    let config = &ctx.config;
-
-    if let Ok(evse_id) = msg.get::<&'static str>("evse_id") {
-        if let Ok(status) = msg.get::<&'static str>("status") {
-            if config.evse_id != evse_id {
-                // ignore messages of other EVSE IDs
-                return Ok(());
-            }
+    if !matches!(auth_state.auth, AuthMsg::Done) {
+        return Ok(());
+    }
 
-            if status == "ended" {
-                // Open the contactor
-                AfbSubCall::call_sync(evt.get_apiv4(), config.charge_api, "remote_power", false)?;
-            }
+    // Limit max current to the one stored on the card
+    {
+        let mut ctx = ctx.shared.write().unwrap();
+        if !ctx.enter(api, SessionState::Authorized, "eim login") {
+            return Ok(());
         }
+        session_saved(api, ctx.session.set_authorized_token(Some(auth_state.tagid.clone())));
+        ctx.auth_max_current = Some(auth_state.imax as f32);
+
+        // Make Josev aware of the new current limitation
+        ctx.apply_status_and_limits();
+        ctx.cs_status_and_limits_event
+            .push(ctx.cs_status_and_limits.clone());
+    };
+
+    if auth_state.ocpp_check {
+        // We ask josev for an authorization with this token.
+        // It will be forwarded to OCPP
+        subcall::send(
+            api,
+            "to_mqtt",
+            "authorization",
+            josev::AuthorizationRequest {
+                evse_id: Some(evse_id.to_string()),
+                id_token: Some(auth_state.tagid.clone()),
+                token_type: josev::AuthorizationTokenType::ISO14443,
+            },
+            // forwarded to OCPP, must not be sent twice
+            SubcallPolicy::default().once(),
+        )?;
+    } else {
+        let ctx = ctx.shared.read().unwrap();
+        // Otherwise, no OCPP is involved and we accept the authorization
+        // by issuing an "update" Authorization message
+        ctx.authorization_event.push(josev::AuthorizationUpdate {
+            evse_id: evse_id.to_string(),
+            token_type: josev::AuthorizationTokenType::ISO14443,
+            status: josev::AuthorizationStatus::Accepted,
+            id_token: Some(auth_state.tagid.clone()),
+        });
     }
     Ok(())
 }
 
-fn charge_limit_evt_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+//
+// ISO 15118-20 SessionStopReq carries ChargingSession = Pause when the EV
+// intends to resume the session later on
+fn session_stop_is_pause(msg: &JsoncObj, dialect: Option<Dialect>) -> bool {
+    if let Ok(info) = msg.get::<JsoncObj>("info") {
+        // key naming differs between python and rust versions of iso15118
+        for key in compat::charging_session_keys(dialect) {
+            if let Ok(charging_session) = info.get::<&'static str>(key) {
+                return charging_session.eq_ignore_ascii_case("pause");
+            }
+        }
+    }
+    false
+}
 
-    let msg = args.get::<JsoncObj>(0)?;
+fn on_hlc_charging(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let msg = match args.get::<&josev::HlcChargingUpdate>(0) {
+        Ok(msg) => msg,
+        Err(error) => {
+            afb_log_msg!(Error, evt.get_apiv4(), "Malformed hlc_charging message: {}", error);
+            return Ok(());
+        }
+    };
     let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
     let config = &ctx.config;
 
-    if let Ok(evse_id) = msg.get::<&'static str>("evse_id") {
-        if let Ok(limit) = msg.get::<f64>("limit") {
-            if config.evse_id != evse_id {
-                // ignore messages of other EVSE IDs
-                return Ok(());
-            }
-            if limit < 0.0 {
-                let iso_state = IsoState::Iso20Discharge;
-                // store the iso state in the context
-                let mut ctx = ctx.shared.write().unwrap();
-                ctx.iso_state = Some(iso_state);
+    if config.evse_id != msg.evse_id {
+        // ignore messages of other EVSE IDs
+        return Ok(());
+    }
 
-                // Discharge case
-                AfbSubCall::call_sync(
-                    evt.get_apiv4(),
-                    config.charge_api,
-                    "iso-state",
-                    ChargingMsg::Iso(iso_state),
-                )?;
-            } else {
-                // if the limit set back to positive during discharge, push the iso20 state
-                let mut ctx = ctx.shared.write().unwrap();
-                if let Some(current_iso_state) = ctx.iso_state {        
-                    match current_iso_state {
-                        IsoState::Iso20Discharge => {
-                            let iso_state = IsoState::Iso20;
-                            ctx.iso_state = Some(iso_state); 
-                            // Charge case
-                            AfbSubCall::call_sync(
-                                evt.get_apiv4(),
-                                config.charge_api,
-                                "iso-state",
-                                ChargingMsg::Iso(iso_state),
-                            )?;
-                        }
-                        _ => {}
-                    }
-                }
+    let status = msg.status;
+    let (entered, iso_state) = {
+        // PowerDelivery stop without SessionStop is a pause, the session
+        // is only considered stopped on SessionStop or transaction end
+        let mut ctx = ctx.shared.write().unwrap();
+        let entered = if status {
+            ctx.enter(evt.get_apiv4(), SessionState::Charging, "power delivery start")
+        } else {
+            ctx.enter(evt.get_apiv4(), SessionState::Paused, "power delivery stop")
+        };
+        (entered, ctx.session.iso_state())
+    };
+
+    // Close (or open on pause) the contactor, when the policy of
+    // the negotiated protocol says PowerDelivery is the right time
+    let apply = if status {
+        config
+            .contactor_policy
+            .should_close(iso_state, ContactorTrigger::PowerDelivery)
+    } else {
+        config
+            .contactor_policy
+            .should_open(iso_state, ContactorTrigger::PowerDelivery)
+    };
+    if entered && apply {
+        remote_power(evt.get_apiv4(), ctx, status)?;
+    }
+
+    {
+        let ctx = ctx.shared.read().unwrap();
+        ctx.cp_status_event.push(ctx.cp_status_update(ctx.cp_state()));
+    }
+    Ok(())
+}
+
+fn on_transaction_status(
+    evt: &AfbEventMsg,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let msg = match args.get::<&josev::TransactionStatusUpdate>(0) {
+        Ok(msg) => msg,
+        Err(error) => {
+            afb_log_msg!(Error, evt.get_apiv4(), "Malformed transaction_status message: {}", error);
+            return Ok(());
+        }
+    };
+    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
+    let config = &ctx.config;
+
+    if config.evse_id != msg.evse_id {
+        // ignore messages of other EVSE IDs
+        return Ok(());
+    }
+
+    let update = {
+        let mut ctx = ctx.shared.write().unwrap();
+        let record = match ctx.transactions.get_mut(&msg.evse_id) {
+            Some(record) if !record.is_new(msg) => {
+                record.update(msg);
+                record.clone()
+            }
+            _ => {
+                let record = TransactionRecord::new(msg);
+                ctx.transactions.insert(msg.evse_id.clone(), record.clone());
+                record
             }
+        };
+        record.charging_msg()
+    };
+    subcall::send(
+        evt.get_apiv4(),
+        config.charge_api,
+        "transaction",
+        update,
+        config.subcalls.charge_api,
+    )?;
+
+    if msg.status == josev::TransactionStatus::Ended {
+        let stopping = {
+            // the session is over, a pause cannot be resumed anymore
+            let mut ctx = ctx.shared.write().unwrap();
+            // after unplugging or a fault, there is nothing left to stop
+            let stopping = !matches!(ctx.session.state(), SessionState::Idle | SessionState::Fault)
+                && ctx.enter(evt.get_apiv4(), SessionState::Stopping, "transaction ended");
+            session_saved(evt.get_apiv4(), ctx.session.set_authorized_token(None));
+            stopping
+        };
+
+        if stopping {
+            // Open the contactor
+            remote_power(evt.get_apiv4(), ctx, false)?;
+            unlock_at_session_end(evt.get_apiv4(), ctx)?;
         }
     }
     Ok(())
 }
 
+fn charge_limit_evt_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let msg = match args.get::<&josev::ChargeLimitUpdate>(0) {
+        Ok(msg) => msg,
+        Err(error) => {
+            afb_log_msg!(Error, evt.get_apiv4(), "Malformed iso15118_charge_limit message: {}", error);
+            return Ok(());
+        }
+    };
+    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
+    let config = &ctx.config;
+
+    if config.evse_id != msg.evse_id {
+        // ignore messages of other EVSE IDs
+        return Ok(());
+    }
+
+    // only the context update is done under the lock,
+    // the charge manager is notified once it is released
+    let iso_state = {
+        let mut ctx = ctx.shared.write().unwrap();
+        if msg.limit < 0.0 {
+            // Discharge case
+            session_saved(
+                evt.get_apiv4(),
+                ctx.session.set_iso_state(Some(IsoState::Iso20Discharge)),
+            );
+            ctx.session.iso_state()
+        } else if let Some(IsoState::Iso20Discharge) = ctx.session.iso_state() {
+            // if the limit set back to positive during discharge, push the iso20 state
+            session_saved(evt.get_apiv4(), ctx.session.set_iso_state(Some(IsoState::Iso20)));
+            ctx.session.iso_state()
+        } else {
+            None
+        }
+    };
+
+    if let Some(iso_state) = iso_state {
+        subcall::send(
+            evt.get_apiv4(),
+            config.charge_api,
+            "iso-state",
+            ChargingMsg::Iso(iso_state),
+            config.subcalls.charge_api,
+        )?;
+    }
+    Ok(())
+}
 
 fn on_slac_status(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
     let msg: &josev::SlacStatusUpdate = args.get::<&josev::SlacStatusUpdate>(0)?;
     let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
     let config = &ctx.config;
 
     if msg.evse_id != config.evse_id {
@@ -479,11 +1856,33 @@ fn on_slac_status(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Res
         josev::SlacStatusUpdateStatus::Failed => SlacStatus::UNMATCHED,
         josev::SlacStatusUpdateStatus::BasicCharging => SlacStatus::TIMEOUT,
     };
-    AfbSubCall::call_sync(
+
+    {
+        let mut ctx = ctx.shared.write().unwrap();
+        match msg.status {
+            josev::SlacStatusUpdateStatus::Matching => {
+                ctx.enter(evt.get_apiv4(), SessionState::Slac, "slac matching");
+            }
+            josev::SlacStatusUpdateStatus::Matched => {
+                ctx.enter(evt.get_apiv4(), SessionState::HlcNegotiation, "slac matched");
+            }
+            _ => {
+                // only the negotiation ends, basic charging may already be going on
+                if matches!(
+                    ctx.session.state(),
+                    SessionState::Slac | SessionState::HlcNegotiation
+                ) {
+                    ctx.enter(evt.get_apiv4(), SessionState::Plugged, "basic charging");
+                }
+            }
+        }
+    }
+    subcall::send(
         evt.get_apiv4(),
         config.charge_api,
         "set_slac_status",
         slac_status,
+        config.subcalls.charge_api,
     )?;
     Ok(())
 }
@@ -495,11 +1894,68 @@ fn on_service_status(
 ) -> Result<(), AfbError> {
     let msg: &josev::ServiceStatusUpdate = args.get::<&josev::ServiceStatusUpdate>(0)?;
     let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
     let config = &ctx.config;
 
+    let compatible = {
+        let mut ctx = ctx.shared.write().unwrap();
+        let compatible = match compat::check(&config.api_versions, msg.service, &msg.api_version) {
+            Compatibility::Supported(dialect) => {
+                if dialect.is_some() {
+                    ctx.dialect = dialect;
+                }
+                true
+            }
+            Compatibility::Unsupported => {
+                afb_log_msg!(
+                    Error,
+                    evt.get_apiv4(),
+                    "Josev {} api {} is not supported",
+                    msg.service,
+                    msg.api_version
+                );
+                false
+            }
+            Compatibility::Unknown => {
+                afb_log_msg!(
+                    Warning,
+                    evt.get_apiv4(),
+                    "Josev {} api {} was not validated with this binding",
+                    msg.service,
+                    msg.api_version
+                );
+                config.unknown_api_version == UnknownVersionPolicy::Warn
+            }
+        };
+        let changed = if compatible {
+            ctx.incompatible.remove(&msg.service)
+        } else {
+            ctx.incompatible.insert(msg.service)
+        };
+        if changed && ctx.apply_status_and_limits() {
+            ctx.cs_status_and_limits_event
+                .push(ctx.cs_status_and_limits.clone());
+        }
+
+        if ctx.services.update(msg) {
+            afb_log_msg!(
+                Notice,
+                evt.get_apiv4(),
+                "Josev {} ready (software {}, api {}), resync",
+                msg.service,
+                msg.software_version,
+                msg.api_version
+            );
+            ctx.resync();
+        }
+        compatible
+    };
+
     let service_str = msg.service.to_string();
 
+    // a service the binding cannot talk to is as good as failed
     let status = match msg.status {
+        _ if !compatible => ServiceStatus::Error,
         josev::ServiceStatusStatus::Ready => ServiceStatus::Ready,
         josev::ServiceStatusStatus::Starting => ServiceStatus::Starting,
         josev::ServiceStatusStatus::Stopping => ServiceStatus::Stopping,
@@ -507,219 +1963,766 @@ fn on_service_status(
         josev::ServiceStatusStatus::Busy => ServiceStatus::Error,
     };
 
-    let mut args = AfbParams::new();
-    args.push(service_str)?;
-    args.push(status)?;
-    AfbSubCall::call_sync(
+    subcall::call_with(
         evt.get_apiv4(),
         config.charge_api,
         "set-service-status",
-        args,
+        config.subcalls.charge_api,
+        move || {
+            let mut args = AfbParams::new();
+            args.push(service_str.clone())?;
+            args.push(status.clone())?;
+            Ok(args)
+        },
+        None,
     )?;
     Ok(())
 }
 
-fn on_contactor_status(
+//
+// Josev update without a handler of its own, re-emitted for other bindings
+struct ForwardCtx {
+    shared: SharedContext,
+    event: &'static AfbEvent,
+}
+
+fn forward_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<ForwardCtx>()?;
+    josev_alive(&ctx.shared);
+
+    let msg = args.get::<JsoncObj>(0)?;
+    afb_log_msg!(Debug, evt.get_apiv4(), "Forwarding Josev {}", evt.get_name());
+    ctx.event.push(msg);
+    Ok(())
+}
+
+//
+// Subscribe (true) or unsubscribe (false) to the forwarded Josev messages
+fn on_subscribe_josev(
+    request: &AfbRequest,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let events = ctx.get_ref::<Vec<&'static AfbEvent>>()?;
+    let subscribe = args.get::<bool>(0)?;
+
+    for event in events {
+        if subscribe {
+            event.subscribe(request)?;
+        } else {
+            event.unsubscribe(request)?;
+        }
+    }
+
+    request.reply(AFB_NO_DATA, 0);
+    Ok(())
+}
+
+// any message from Josev, for any EVSE, proves the link is up
+fn josev_alive(ctx: &SharedContext) {
+    ctx.shared.write().unwrap().josev_alive();
+}
+
+// tell the charge manager about the ISO 15118 stack
+fn set_iso_service_status(
+    api: AfbApiV4,
+    ctx: &SharedContext,
+    status: ServiceStatus,
+) -> Result<(), AfbError> {
+    let service_str = josev::Service::Iso15118.to_string();
+    subcall::call_with(
+        api,
+        ctx.config.charge_api,
+        "set-service-status",
+        ctx.config.subcalls.charge_api,
+        move || {
+            let mut args = AfbParams::new();
+            args.push(service_str.clone())?;
+            args.push(status.clone())?;
+            Ok(args)
+        },
+        None,
+    )
+}
+
+//
+// Josev (or the broker) stopped talking: the ISO stack is unavailable,
+// the charge manager falls back to basic charging
+fn on_josev_lost(api: AfbApiV4, ctx: &SharedContext, silence: Duration) -> Result<(), AfbError> {
+    let config = &ctx.config;
+    afb_log_msg!(
+        Error,
+        api,
+        "No message from Josev for {} ms, falling back to basic charging",
+        silence.as_millis()
+    );
+
+    {
+        let mut ctx = ctx.shared.write().unwrap();
+        if ctx.session.state() == SessionState::Slac {
+            ctx.enter(api, SessionState::Plugged, "josev lost");
+        }
+    }
+
+    set_iso_service_status(api, ctx, ServiceStatus::Error)?;
+    subcall::send(
+        api,
+        config.charge_api,
+        "set_slac_status",
+        SlacStatus::TIMEOUT,
+        config.subcalls.charge_api,
+    )?;
+    Ok(())
+}
+
+//
+// Josev is back, it may have missed anything that happened meanwhile
+fn on_josev_found(api: AfbApiV4, ctx: &SharedContext) -> Result<(), AfbError> {
+    afb_log_msg!(Notice, api, "Josev is back, resync");
+
+    let compatible = {
+        let ctx = ctx.shared.read().unwrap();
+        ctx.resync();
+        !ctx.incompatible.contains(&josev::Service::Iso15118)
+    };
+
+    let status = if compatible {
+        ServiceStatus::Ready
+    } else {
+        ServiceStatus::Error
+    };
+    set_iso_service_status(api, ctx, status)
+}
+
+fn watchdog_timer_cb(timer: &AfbTimer, _decount: u32, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    let config = &ctx.config;
+    let Some(watchdog) = config.watchdog else {
+        return Ok(());
+    };
+    let api = timer.get_apiv4();
+
+    let timeout = Duration::from_millis(watchdog.timeout as u64);
+    let (silence, lost, found, quiet) = {
+        let mut ctx = ctx.shared.write().unwrap();
+        // an idle station, a paused EV or basic charging expect nothing from Josev
+        if !ctx.session.is_hlc() {
+            ctx.josev_watched_since = None;
+        } else if ctx.josev_watched_since.is_none() {
+            ctx.josev_watched_since = Some(Instant::now());
+        }
+        let silence = match ctx.josev_watched_since {
+            Some(since) => ctx.last_josev_message.elapsed().min(since.elapsed()),
+            None => Duration::ZERO,
+        };
+        let lost = silence >= timeout && !ctx.josev_lost;
+        // once lost, Josev is back when it talks again, whatever the session
+        let found = ctx.josev_lost && ctx.last_josev_message.elapsed() < timeout;
+        if lost || found {
+            ctx.josev_lost = lost;
+        }
+        let quiet = ctx.josev_lost || silence >= timeout / 2;
+        (silence, lost, found, quiet)
+    };
+
+    if lost {
+        on_josev_lost(api, ctx, silence)?;
+    } else if found {
+        on_josev_found(api, ctx)?;
+    }
+
+    // quiet for a while (or gone), ask for a sign of life
+    if let Some(ping) = watchdog.ping {
+        if quiet {
+            let shared = ctx.clone();
+            let evse_id = config.evse_id.clone();
+            subcall::call_with(
+                api,
+                "to_mqtt",
+                ping,
+                SubcallPolicy {
+                    timeout: watchdog.period(),
+                    retries: 0,
+                    backoff: 0,
+                },
+                move || {
+                    let args = JsoncObj::new();
+                    args.add("evse_id", evse_id.as_str())?;
+                    Ok(args)
+                },
+                Some(Box::new(move |_api, reply| {
+                    if reply.is_ok() {
+                        josev_alive(&shared);
+                    }
+                    Ok(())
+                })),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn on_contactor_status(
+    request: &AfbRequest,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let arg = args.get::<&josev::CsContactorStatusRequest>(0)?;
+    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
+
+    let response = {
+        let ctx = ctx.shared.read().unwrap();
+
+        if ctx.cs_parameters.parameters[0].evse_id != arg.evse_id {
+            request.reply(
+                josev::CsContactorStatusResponse {
+                    evse_id: arg.evse_id.clone(),
+                    status: josev::CsContactorStatusResponseStatus::Error,
+                    info: Some("unknown evse_id".to_string()),
+                },
+                0,
+            );
+            return Ok(());
+        }
+
+        josev::CsContactorStatusResponse {
+            evse_id: arg.evse_id.clone(),
+            status: {
+                if let Some(closed) = ctx.forced_contactor_closed {
+                    if closed {
+                        josev::CsContactorStatusResponseStatus::Closed
+                    } else {
+                        josev::CsContactorStatusResponseStatus::Opened
+                    }
+                } else {
+                    if ctx.session.contactor_closed() {
+                        josev::CsContactorStatusResponseStatus::Closed
+                    } else {
+                        josev::CsContactorStatusResponseStatus::Opened
+                    }
+                }
+            },
+            info: None,
+        }
+    };
+
+    request.reply(response, 0);
+    Ok(())
+}
+
+fn on_status_and_limits(
+    request: &AfbRequest,
+    _args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    afb_log_msg!(Debug, request.get_apiv4(), "CS STATUS AND LIMITS");
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
+    let ctx = ctx.shared.read().unwrap();
+    request.reply(ctx.cs_status_and_limits.clone(), 0);
+    Ok(())
+}
+
+fn on_cs_parameters(
+    request: &AfbRequest,
+    _args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    afb_log_msg!(Debug, request.get_apiv4(), "CS PARAMETERS");
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
+    let ctx = ctx.shared.read().unwrap();
+    request.reply(ctx.cs_parameters.clone(), 0);
+    Ok(())
+}
+
+fn on_device_model(
+    request: &AfbRequest,
+    _args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    afb_log_msg!(Debug, request.get_apiv4(), "DEVICE MODEL");
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
+    let ctx = ctx.shared.read().unwrap();
+    match ctx.device_model.clone() {
+        Some(device_model) => request.reply(device_model, 0),
+        None => {
+            let error = AfbError::new(JOSEV_API, -1, "no device_model configured");
+            request.reply(afb_add_trace!(error), -1);
+        }
+    }
+    Ok(())
+}
+
+//
+// A stop_charging request, replied once the charge manager
+// reports the contactor open, or rejected on timeout
+struct OpenWaiter {
+    id: u64,
+    request: AfbRequest,
+    evse_id: String,
+}
+
+struct OpenTimeoutCtx {
+    shared: SharedContext,
+    id: u64,
+}
+
+// reply to the stop_charging requests waiting for the contactor to open,
+// all of them, or only the one with the given id
+fn reply_open_waiters(ctx: &SharedContext, id: Option<u64>, status: josev::MessageStatus) {
+    let waiters: Vec<OpenWaiter> = {
+        let mut ctx = ctx.shared.write().unwrap();
+        let (done, pending) = ctx
+            .open_waiters
+            .drain(..)
+            .partition(|waiter| id.is_none_or(|id| waiter.id == id));
+        ctx.open_waiters = pending;
+        done
+    };
+
+    for waiter in waiters {
+        waiter.request.reply(
+            josev::StopChargingResponse {
+                evse_id: waiter.evse_id,
+                status,
+            },
+            0,
+        );
+    }
+}
+
+fn stop_charging_timeout_cb(
+    timer: &AfbTimer,
+    _decount: u32,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<OpenTimeoutCtx>()?;
+    let pending = {
+        let shared = ctx.shared.shared.read().unwrap();
+        shared.open_waiters.iter().any(|waiter| waiter.id == ctx.id)
+    };
+    if pending {
+        afb_log_msg!(Error, timer.get_apiv4(), "stop_charging: contactor still closed");
+        reply_open_waiters(&ctx.shared, Some(ctx.id), josev::MessageStatus::Rejected);
+    }
+    Ok(())
+}
+
+fn on_stop_charging(
+    request: &AfbRequest,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let arg = args.get::<&josev::StopChargingRequest>(0)?;
+    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
+
+    let id = {
+        let mut ctx = ctx.shared.write().unwrap();
+
+        let status = if ctx.cs_parameters.parameters[0].evse_id != arg.evse_id {
+            Some(josev::MessageStatus::Rejected)
+        } else if !ctx.relay_closed.unwrap_or(ctx.session.contactor_closed()) {
+            // nothing to wait for
+            Some(josev::MessageStatus::Accepted)
+        } else {
+            None
+        };
+        if let Some(status) = status {
+            request.reply(
+                josev::StopChargingResponse {
+                    evse_id: arg.evse_id.clone(),
+                    status,
+                },
+                0,
+            );
+            return Ok(());
+        }
+
+        ctx.last_waiter += 1;
+        let id = ctx.last_waiter;
+        ctx.open_waiters.push(OpenWaiter {
+            id,
+            request: request.add_ref(),
+            evse_id: arg.evse_id.clone(),
+        });
+        id
+    };
+
+    AfbTimer::new("josev-stop-charging")
+        .set_period(ctx.config.stop_charging_timeout)
+        .set_decount(1)
+        .set_callback(stop_charging_timeout_cb)
+        .set_context(OpenTimeoutCtx {
+            shared: ctx.clone(),
+            id,
+        })
+        .start()?;
+
+    // Open the contactor
+    if let Err(error) = remote_power(request.get_apiv4(), ctx, false) {
+        afb_log_msg!(Error, request.get_apiv4(), "stop_charging failed: {}", error);
+        reply_open_waiters(ctx, Some(id), josev::MessageStatus::Rejected);
+    }
+    Ok(())
+}
+
+fn on_cp_pwm(request: &AfbRequest, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let arg: &josev::CpPwmRequest = args.get::<&josev::CpPwmRequest>(0)?;
+    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
+
+    {
+        let ctx = ctx.shared.read().unwrap();
+
+        if ctx.cs_parameters.parameters[0].evse_id != arg.evse_id {
+            request.reply(
+                josev::CpPwmResponse {
+                    evse_id: arg.evse_id.clone(),
+                    status: josev::CpPwmResponseStatus::Error,
+                    info: Some("unknown evse_id".to_string()),
+                },
+                0,
+            );
+            return Ok(());
+        }
+    }
+
+    // CP PWM should be requested only for HLC (5% PWM)
+    // which is already taken care of by the M4 firmware
+    // So we always answer Valid here
+    request.reply(
+        josev::CpPwmResponse {
+            evse_id: arg.evse_id.clone(),
+            status: josev::CpPwmResponseStatus::Valid,
+            info: None,
+        },
+        0,
+    );
+    Ok(())
+}
+
+fn connector_lock_reply(
+    request: &AfbRequest,
+    arg: &josev::ConnectorLockRequest,
+    ctx: &SharedContext,
+    lock: bool,
+) -> Result<(), AfbError> {
+    let reply = |status: josev::MessageStatus, info: Option<&str>| {
+        request.reply(
+            josev::ConnectorLockResponse {
+                evse_id: arg.evse_id.clone(),
+                status,
+                info: info.map(|info| info.to_string()),
+            },
+            0,
+        );
+    };
+
+    {
+        let ctx = ctx.shared.read().unwrap();
+
+        if ctx.cs_parameters.parameters[0].evse_id != arg.evse_id {
+            reply(josev::MessageStatus::Rejected, Some("unknown evse_id"));
+            return Ok(());
+        }
+
+        if !lock && ctx.session.contactor_closed() {
+            // never unlock a cable under load
+            reply(josev::MessageStatus::Rejected, Some("contactor is closed"));
+            return Ok(());
+        }
+    }
+
+    // the reply is sent once the charge manager has answered
+    let request = request.add_ref();
+    let evse_id = arg.evse_id.clone();
+    // a lock request that cannot be sent is rejected by the continuation as well
+    remote_lock(
+        request.get_apiv4(),
+        ctx,
+        lock,
+        Some(Box::new(move |_api, status| {
+            let (status, info) = match status {
+                Ok(()) => (josev::MessageStatus::Accepted, None),
+                Err(error) => (josev::MessageStatus::Rejected, Some(error.to_string())),
+            };
+            request.reply(
+                josev::ConnectorLockResponse {
+                    evse_id,
+                    status,
+                    info,
+                },
+                0,
+            );
+            Ok(())
+        })),
+    )
+}
+
+fn on_lock_connector(
     request: &AfbRequest,
     args: &AfbRqtData,
     ctx: &AfbCtxData,
 ) -> Result<(), AfbError> {
-    let arg = args.get::<&josev::CsContactorStatusRequest>(0)?;
+    let arg = args.get::<&josev::ConnectorLockRequest>(0)?;
     let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    connector_lock_reply(request, arg, ctx, true)
+}
 
-    let response = {
-        let ctx = ctx.shared.read().unwrap();
-
-        if ctx.cs_parameters.parameters[0].evse_id != arg.evse_id {
-            // ignore requests for another EVSE ID
-            return Ok(());
-        }
-
-        josev::CsContactorStatusResponse {
-            evse_id: arg.evse_id.clone(),
-            status: {
-                if let Some(closed) = ctx.forced_contactor_closed {
-                    if closed {
-                        josev::CsContactorStatusResponseStatus::Closed
-                    } else {
-                        josev::CsContactorStatusResponseStatus::Opened
-                    }
-                } else {
-                    if ctx.contactor_closed {
-                        josev::CsContactorStatusResponseStatus::Closed
-                    } else {
-                        josev::CsContactorStatusResponseStatus::Opened
-                    }
-                }
-            },
-            info: None,
-        }
-    };
-
-    request.reply(response, 0);
-    Ok(())
+fn on_unlock_connector(
+    request: &AfbRequest,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let arg = args.get::<&josev::ConnectorLockRequest>(0)?;
+    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    connector_lock_reply(request, arg, ctx, false)
 }
 
-fn on_status_and_limits(
+fn on_meter_values(
     request: &AfbRequest,
-    _args: &AfbRqtData,
+    args: &AfbRqtData,
     ctx: &AfbCtxData,
 ) -> Result<(), AfbError> {
-    afb_log_msg!(Debug, request.get_apiv4(), "CS STATUS AND LIMITS");
-    let ctx = ctx.get_ref::<SharedContext>()?;
-    let ctx = ctx.shared.read().unwrap();
-    request.reply(ctx.cs_status_and_limits.clone(), 0);
+    afb_log_msg!(Debug, request.get_apiv4(), "METER_VALUES");
+    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
+    let config = &ctx.config;
+
+    let req: &josev::MeterValuesRequest = args.get::<&josev::MeterValuesRequest>(0)?;
+
+    // read voltage, current and total energy, the reply is sent once the three
+    // readings are received, a failing meter only fails this request
+    let reading = Arc::new(Mutex::new(MeterReading {
+        request: request.add_ref(),
+        query: req.clone(),
+        values: [None, None, None],
+        error: None,
+        pending: METER_READINGS.len(),
+    }));
+    for (index, verb) in METER_READINGS.iter().enumerate() {
+        let done = reading.clone();
+        subcall::call_with(
+            request.get_apiv4(),
+            config.meter_api,
+            verb,
+            config.subcalls.meter_api,
+            || Ok(EnergyAction::READ),
+            Some(Box::new(move |_api, reply| {
+                let value = reply.and_then(|reply| reply.get_onsuccess::<&MeterDataSet>(0).cloned());
+                let mut reading = done.lock().unwrap();
+                match value {
+                    Ok(value) => reading.values[index] = Some(value),
+                    Err(error) => reading.error = Some(error),
+                }
+                reading.pending -= 1;
+                if reading.pending == 0 {
+                    reading.reply();
+                }
+                Ok(())
+            })),
+        )?;
+    }
     Ok(())
 }
 
-fn on_cs_parameters(
+// meter verbs read by meter_values, in MeterReading::values order
+const METER_READINGS: [&str; 3] = ["tension", "current", "energy"];
+
+struct MeterReading {
+    request: AfbRequest,
+    query: josev::MeterValuesRequest,
+    values: [Option<MeterDataSet>; 3],
+    error: Option<AfbError>,
+    pending: usize,
+}
+
+impl MeterReading {
+    fn reply(&self) {
+        let (voltage, current, energy) = match (&self.values, &self.error) {
+            ([Some(voltage), Some(current), Some(energy)], None) => (voltage, current, energy),
+            (_, error) => {
+                let error = match error {
+                    Some(error) => error.clone(),
+                    None => AfbError::new(JOSEV_API, -1, "meter reading missing"),
+                };
+                self.request.reply(afb_add_trace!(error), -1);
+                return;
+            }
+        };
+
+        let response = josev::MeterValuesResponse {
+            evse_id: self.query.evse_id.clone(),
+            timestamp: self.query.timestamp,
+            voltage: josev::MeterValuesUpdateVoltage {
+                l1: voltage.l1 as f32 / 1000.0,
+                l2: voltage.l2 as f32 / 1000.0,
+                l3: voltage.l3 as f32 / 1000.0,
+            },
+            current: josev::MeterValuesUpdateCurrent {
+                l1: current.l1 as f32 / 1000.0,
+                l2: current.l2 as f32 / 1000.0,
+                l3: current.l3 as f32 / 1000.0,
+            },
+            power_factor: 1.0,
+            dc_current: None,
+            dc_voltage: None,
+            frequency: 50.0,
+            total_active_energy_imported: energy.total as f32,
+            total_active_energy_exported: None,
+            total_reactive_energy_imported: 0.0,
+            total_reactive_energy_exported: None,
+            soc: None,
+            signed_meter_values: None,
+        };
+
+        self.request.reply(response, 0);
+    }
+}
+
+fn on_service_health(
     request: &AfbRequest,
     _args: &AfbRqtData,
     ctx: &AfbCtxData,
 ) -> Result<(), AfbError> {
-    afb_log_msg!(Debug, request.get_apiv4(), "CS PARAMETERS");
     let ctx = ctx.get_ref::<SharedContext>()?;
     let ctx = ctx.shared.read().unwrap();
-    request.reply(ctx.cs_parameters.clone(), 0);
+    request.reply(ctx.services.status(), 0);
     Ok(())
 }
 
-fn on_device_model(
+fn on_session_state(
     request: &AfbRequest,
     _args: &AfbRqtData,
     ctx: &AfbCtxData,
 ) -> Result<(), AfbError> {
-    afb_log_msg!(Debug, request.get_apiv4(), "DEVICE MODEL");
     let ctx = ctx.get_ref::<SharedContext>()?;
     let ctx = ctx.shared.read().unwrap();
-    if let Some(device_model) = ctx.device_model.clone() {
-        request.reply(device_model, 0);
-    }
+    request.reply(ctx.session.status(), 0);
     Ok(())
 }
 
-fn on_stop_charging(
+//
+// OCPP ChangeAvailability, forwarded by Josev
+fn on_change_availability(
     request: &AfbRequest,
     args: &AfbRqtData,
     ctx: &AfbCtxData,
 ) -> Result<(), AfbError> {
-    let arg = args.get::<&josev::StopChargingRequest>(0)?;
-    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
-
-    {
-        let ctx = ctx.shared.read().unwrap();
+    let arg = args.get::<&josev::ChangeAvailabilityRequest>(0)?;
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    josev_alive(ctx);
 
-        if &ctx.cs_parameters.parameters[0].evse_id != &arg.evse_id {
-            // ignore requests for another EVSE ID
-            return Ok(());
+    let status = if ctx.config.evse_id != arg.evse_id {
+        josev::ChangeAvailabilityStatus::Rejected
+    } else {
+        match set_availability(request.get_apiv4(), ctx, arg.operational_status) {
+            Ok(status) => status,
+            Err(error) => {
+                afb_log_msg!(Error, request.get_apiv4(), "change_availability failed: {}", error);
+                josev::ChangeAvailabilityStatus::Rejected
+            }
         }
-    }
-
-    // Open the contactor
-    AfbSubCall::call_sync(
-        request.get_apiv4(),
-        ctx.config.charge_api,
-        "remote_power",
-        false,
-    )?;
+    };
 
     request.reply(
-        josev::StopChargingResponse {
+        josev::ChangeAvailabilityResponse {
             evse_id: arg.evse_id.clone(),
-            status: josev::MessageStatus::Accepted,
+            status,
         },
         0,
     );
     Ok(())
 }
 
-fn on_cp_pwm(request: &AfbRequest, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
-    let arg: &josev::CpPwmRequest = args.get::<&josev::CpPwmRequest>(0)?;
-    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
-
-    {
-        let ctx = ctx.shared.read().unwrap();
+//
+// Current availability, changed when an operational_status is given
+fn on_availability(
+    request: &AfbRequest,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    // without argument, only read the availability
+    let args = verb_args::<AvailabilityArgs>(args)?;
 
-        if &ctx.cs_parameters.parameters[0].evse_id != &arg.evse_id {
-            // ignore requests for another EVSE ID
-            return Ok(());
-        }
+    if let Some(operational_status) = args.operational_status {
+        set_availability(request.get_apiv4(), ctx, operational_status)?;
     }
 
-    // CP PWM should be requested only for HLC (5% PWM)
-    // which is already taken care of by the M4 firmware
-    // So we always answer Valid here
-    request.reply(
-        josev::CpPwmResponse {
-            evse_id: arg.evse_id.clone(),
-            status: josev::CpPwmResponseStatus::Valid,
-            info: None,
-        },
-        0,
-    );
+    let evse_id = ctx.config.evse_id.clone();
+    let status = {
+        let ctx = ctx.shared.read().unwrap();
+        AvailabilityStatus {
+            evse_id,
+            operational_status: if ctx.availability == Availability::Operative {
+                josev::OperationalStatus::Operative
+            } else {
+                josev::OperationalStatus::Inoperative
+            },
+            scheduled: ctx.availability == Availability::Scheduled,
+            status_code: ctx.status_code(),
+        }
+    };
+    request.reply(status, 0);
     Ok(())
 }
 
-fn on_meter_values(
+fn on_emergency_stop(
     request: &AfbRequest,
     args: &AfbRqtData,
     ctx: &AfbCtxData,
 ) -> Result<(), AfbError> {
-    afb_log_msg!(Debug, request.get_apiv4(), "METER_VALUES");
-    let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
-    let config = &ctx.config;
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    // the argument is optional
+    let reason = verb_args::<EmergencyStopArgs>(args)?
+        .reason
+        .unwrap_or_else(|| "emergency_stop verb".to_string());
 
-    let req: &josev::MeterValuesRequest = args.get::<&josev::MeterValuesRequest>(0)?;
+    emergency_stop(request.get_apiv4(), ctx, reason)?;
+    request.reply(AFB_NO_DATA, 0);
+    Ok(())
+}
 
-    // read voltage, current and total energy
-    let voltage = AfbSubCall::call_sync(
-        request.get_apiv4(),
-        config.meter_api,
-        "tension",
-        EnergyAction::READ,
-    )?;
-    let voltage: &MeterDataSet = voltage.get_onsuccess::<&MeterDataSet>(0)?;
-    let current = AfbSubCall::call_sync(
-        request.get_apiv4(),
-        config.meter_api,
-        "current",
-        EnergyAction::READ,
-    )?;
-    let current: &MeterDataSet = current.get_onsuccess::<&MeterDataSet>(0)?;
-    let energy = AfbSubCall::call_sync(
-        request.get_apiv4(),
-        config.meter_api,
-        "energy",
-        EnergyAction::READ,
-    )?;
-    let energy: &MeterDataSet = energy.get_onsuccess::<&MeterDataSet>(0)?;
-
-    let response = josev::MeterValuesResponse {
-        evse_id: req.evse_id.clone(),
-        timestamp: req.timestamp.clone(),
-        voltage: josev::MeterValuesUpdateVoltage {
-            l1: voltage.l1 as f32 / 1000.0,
-            l2: voltage.l2 as f32 / 1000.0,
-            l3: voltage.l3 as f32 / 1000.0,
-        },
-        current: josev::MeterValuesUpdateCurrent {
-            l1: current.l1 as f32 / 1000.0,
-            l2: current.l2 as f32 / 1000.0,
-            l3: current.l3 as f32 / 1000.0,
-        },
-        power_factor: 1.0,
-        dc_current: None,
-        dc_voltage: None,
-        frequency: 50.0,
-        total_active_energy_imported: energy.total as f32,
-        total_active_energy_exported: None,
-        total_reactive_energy_imported: 0.0,
-        total_reactive_energy_exported: None,
-        soc: None,
-        signed_meter_values: None,
-    };
+fn on_rearm(request: &AfbRequest, _args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    emergency_rearm(request.get_apiv4(), ctx)?;
+    request.reply(AFB_NO_DATA, 0);
+    Ok(())
+}
 
-    request.reply(response, 0);
+//
+// Current (or last) transaction of an EVSE, for billing
+fn on_transaction(
+    request: &AfbRequest,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<SharedContext>()?;
+    let evse_id = verb_args::<TransactionArgs>(args)?
+        .evse_id
+        .unwrap_or_else(|| ctx.config.evse_id.clone());
+
+    let ctx = ctx.shared.read().unwrap();
+    match ctx.transactions.get(&evse_id) {
+        Some(record) => request.reply(record.clone(), 0),
+        None => {
+            let error = AfbError::new(JOSEV_API, -1, format!("no transaction for {}", evse_id));
+            request.reply(afb_add_trace!(error), -1);
+        }
+    }
+    Ok(())
+}
+
+//
+// Verbs and events of the api, with the JSON Schemas of their payloads
+fn on_info(request: &AfbRequest, _args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let info = ctx.get_ref::<String>()?;
+    request.reply(JsoncObj::parse(info)?, 0);
     Ok(())
 }
 
@@ -728,50 +2731,21 @@ fn on_force_cp_state(
     args: &AfbRqtData,
     ctx: &AfbCtxData,
 ) -> Result<(), AfbError> {
-    afb_log_msg!(Debug, request.get_apiv4(), "METER_VALUES");
+    afb_log_msg!(Debug, request.get_apiv4(), "FORCE_CP_STATE");
     let ctx: &SharedContext = ctx.get_ref::<SharedContext>()?;
 
-    let arg: JsoncObj = args.get::<JsoncObj>(0)?;
-    if let Some(cp) = arg.optional::<&str>("cp")? {
+    let args = verb_args::<ForceCpStateArgs>(args)?;
+    if let Some(cp) = args.cp {
         let mut ctx = ctx.shared.write().unwrap();
-        match cp {
-            "A1" => ctx.forced_charging_state = Some(josev::ControlPilotState::A1),
-            "A2" => ctx.forced_charging_state = Some(josev::ControlPilotState::A2),
-            "B1" => ctx.forced_charging_state = Some(josev::ControlPilotState::B1),
-            "B2" => ctx.forced_charging_state = Some(josev::ControlPilotState::B2),
-            "C1" => ctx.forced_charging_state = Some(josev::ControlPilotState::C1),
-            "C2" => ctx.forced_charging_state = Some(josev::ControlPilotState::C2),
-            "E" => ctx.forced_charging_state = Some(josev::ControlPilotState::E),
-            "F" => ctx.forced_charging_state = Some(josev::ControlPilotState::F),
-            _ => {}
-        }
-
-        if let Some(charging_state) = ctx.forced_charging_state {
-            ctx.cp_status_event.push(josev::CpStatusUpdate {
-                evse_id: ctx.cs_parameters.parameters[0].evse_id.clone(),
-                connector_id: ctx.cs_parameters.parameters[0].connectors[0].id,
-                state: charging_state,
-                max_voltage: None,
-                min_voltage: None,
-                duty_cycle: None,
-            });
-        }
+        ctx.forced_charging_state = Some(cp);
+        ctx.cp_status_event.push(ctx.cp_status_update(ctx.cp_state()));
     }
-    if let Some(closed) = arg.optional::<bool>("closed_contactor")? {
+    if let Some(closed) = args.closed_contactor {
         let mut ctx = ctx.shared.write().unwrap();
         ctx.forced_contactor_closed = Some(closed);
+        ctx.contactor_pushed = Some(closed);
         ctx.contactor_status_event
-            .push(josev::CsContactorStatusUpdate {
-                evse_id: ctx.cs_parameters.parameters[0].evse_id.clone(),
-                status: {
-                    if closed {
-                        josev::CsContactorStatusResponseStatus::Closed
-                    } else {
-                        josev::CsContactorStatusResponseStatus::Opened
-                    }
-                },
-                info: None,
-            });
+            .push(ctx.contactor_status_update(closed));
     }
 
     request.reply(AFB_NO_DATA, 0);
@@ -788,7 +2762,10 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
     chmgr_registers()?;
     engy_registers()?;
     slac_registers()?;
+    am62x_registers()?;
     josev::josev_registers()?;
+    session_registers()?;
+    health_registers()?;
 
     let cs_parameters = jconf.get::<JsoncObj>("cs_parameters")?;
     let cs_parameters: josev::CsParametersResponse =
@@ -814,6 +2791,18 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
             afb_error!(JOSEV_API, "'cs_status_and_limits' malformed: {}", error)
         })?;
 
+    if cs_status_and_limits.evses.len() != 1 {
+        return afb_error!(
+            JOSEV_API,
+            "'cs_status_and_limits' must describe exactly one EVSE"
+        );
+    }
+    let evse_status_code = cs_status_and_limits.evses[0].status_code;
+    let evse_max_current = cs_status_and_limits.evses[0]
+        .ac
+        .as_ref()
+        .map(|ac| ac.max_current.clone());
+
     let device_model = jconf.optional::<JsoncObj>("device_model")?;
     let device_model: Option<josev::DeviceModelResponse> = if device_model.is_some() {
         serde_json::from_str(&device_model.unwrap().to_string())
@@ -825,26 +2814,119 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
     let charge_api = jconf.get::<&'static str>("charge_api")?;
     let meter_api = jconf.get::<&'static str>("meter_api")?;
     let auth_api = jconf.get::<&'static str>("auth_api")?;
+    let iec_api = jconf.optional::<&'static str>("iec_api")?;
+    let cp_fault_verb = jconf.optional::<&'static str>("cp_fault_verb")?;
+
+    let contactor_policy = jconf.optional::<JsoncObj>("contactor_policy")?;
+    let contactor_policy: ContactorPolicy = if let Some(contactor_policy) = contactor_policy {
+        serde_json::from_str(&contactor_policy.to_string())
+            .or_else(|error| afb_error!(JOSEV_API, "'contactor_policy' malformed: {}", error))?
+    } else {
+        ContactorPolicy::default()
+    };
+    for rule in [contactor_policy.iso2, contactor_policy.iso20] {
+        if rule.close == ContactorTrigger::SessionStop {
+            return afb_error!(
+                JOSEV_API,
+                "'contactor_policy' cannot close the contactor on session_stop"
+            );
+        }
+    }
+
+    let cp_deadband = jconf.optional::<JsoncObj>("cp_deadband")?;
+    let cp_deadband: CpDeadband = if let Some(cp_deadband) = cp_deadband {
+        serde_json::from_str(&cp_deadband.to_string())
+            .or_else(|error| afb_error!(JOSEV_API, "'cp_deadband' malformed: {}", error))?
+    } else {
+        CpDeadband::default()
+    };
+
+    let subcalls = jconf.optional::<JsoncObj>("subcalls")?;
+    let subcalls: SubcallPolicies = if let Some(subcalls) = subcalls {
+        serde_json::from_str(&subcalls.to_string())
+            .or_else(|error| afb_error!(JOSEV_API, "'subcalls' malformed: {}", error))?
+    } else {
+        SubcallPolicies::default()
+    };
+
+    let stop_charging_timeout = jconf.default::<u32>("stop_charging_timeout", 5000)?;
+    let session_file = jconf.optional::<&'static str>("session_file")?;
+    let unknown_api_version = match jconf.default::<&str>("unknown_api_version", "warn")? {
+        "warn" => UnknownVersionPolicy::Warn,
+        "refuse" => UnknownVersionPolicy::Refuse,
+        other => {
+            return afb_error!(
+                JOSEV_API,
+                "'unknown_api_version' must be warn or refuse, not {}",
+                other
+            )
+        }
+    };
+    let api_versions = jconf.optional::<JsoncObj>("api_versions")?;
+    let api_versions: Vec<VersionRange> = if let Some(api_versions) = api_versions {
+        serde_json::from_str(&api_versions.to_string())
+            .or_else(|error| afb_error!(JOSEV_API, "'api_versions' malformed: {}", error))?
+    } else {
+        Vec::new()
+    };
+    let finish_session_when_inoperative =
+        jconf.default::<bool>("finish_session_when_inoperative", true)?;
+
+    // the MQTT configuration is generated from the names of the messages module
+    debug_assert!(JOSEV_VERBS
+        .iter()
+        .map(|(verb, _)| verb.name)
+        .eq(JOSEV_REQUESTS));
+    debug_assert!(JOSEV_HANDLERS
+        .iter()
+        .map(|(message, _)| *message)
+        .eq(JOSEV_MESSAGES));
+
+    let mut registry = MessageRegistry::new(&JOSEV_HANDLERS);
+    if let Some(josev_messages) = jconf.optional::<JsoncObj>("josev_messages")? {
+        registry.configure(&josev_messages)?;
+    }
 
-    let cp_status_event = AfbEvent::new("cp_status");
-    let authorization_event = AfbEvent::new("authorization");
-    let contactor_status_event = AfbEvent::new("cs_contactor_status");
-    let cs_status_and_limits_event = AfbEvent::new("cs_status_and_limits");
+    let watchdog = match jconf.optional::<JsoncObj>("watchdog")? {
+        Some(watchdog) => {
+            let timeout = watchdog.get::<u32>("timeout")?;
+            if timeout == 0 {
+                return afb_error!(JOSEV_API, "'watchdog' timeout must not be 0");
+            }
+            Some(Watchdog {
+                timeout,
+                ping: watchdog.optional::<&'static str>("ping")?,
+            })
+        }
+        None => None,
+    };
 
-    let charging_state = josev::ControlPilotState::A1;
+    let [cp_status_event, authorization_event, contactor_status_event, cs_status_and_limits_event] =
+        JOSEV_EVENTS.map(AfbEvent::new);
 
     // copy evse_id as immutable configuration
     let evse_id = cs_parameters.parameters[0].evse_id.clone();
 
+    let josev_messages = registry.names();
+
     let config = ApiUserData {
         charge_api,
         auth_api,
         meter_api,
+        iec_api,
+        cp_fault_verb,
+        cp_deadband,
+        contactor_policy,
+        subcalls,
+        stop_charging_timeout,
+        finish_session_when_inoperative,
+        unknown_api_version,
+        api_versions,
+        watchdog,
+        josev_messages: josev_messages.clone(),
         evse_id,
     };
 
-    let api = AfbApi::new(JOSEV_API).set_callback(Box::new(config.clone()));
-
     let shared_context = SharedContext {
         config,
         shared: Arc::new(RwLock::new(Context {
@@ -852,21 +2934,65 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
             authorization_event,
             contactor_status_event,
             cs_status_and_limits_event,
-            charging_state,
+            session: Session::new(session_file),
+            cp_measure: None,
             cs_parameters,
             cs_status_and_limits,
+            evse_status_code,
+            evse_max_current,
+            auth_max_current: None,
+            cable_max_current: None,
             device_model,
-            contactor_closed: false,
-            iso_state: None,
-            payment_option: None,
+            transactions: HashMap::new(),
+            open_waiters: Vec::new(),
+            relay_closed: None,
+            contactor_pushed: None,
+            last_waiter: 0,
+            connector_locked: false,
+            unlock_pending: false,
+            lock_fault: false,
+            emergency: None,
+            cp_fault: false,
+            cp_fault_pending: false,
+            availability: Availability::Operative,
+            shutdown: false,
+            services: Services::default(),
+            dialect: None,
+            incompatible: HashSet::new(),
+            last_josev_message: Instant::now(),
+            josev_lost: false,
+            josev_watched_since: None,
             forced_charging_state: None,
             forced_contactor_closed: None,
         })),
     };
 
+    let api = AfbApi::new(JOSEV_API).set_callback(Box::new(shared_context.clone()));
+
     api.require_api(charge_api);
     api.require_api(meter_api);
     api.require_api(auth_api);
+    match iec_api {
+        Some(iec_api) => {
+            api.require_api(iec_api);
+        }
+        None => {
+            afb_log_msg!(
+                Warning,
+                rootv4,
+                "no 'iec_api' configured: no cable current limiting, no proximity pilot fault, \
+                 stop_charging trusts the charge manager power state"
+            );
+        }
+    }
+    if iec_api.is_none() || cp_fault_verb.is_none() {
+        afb_log_msg!(
+            Warning,
+            rootv4,
+            "no 'iec_api' or 'cp_fault_verb' configured: an emergency stop cannot force the CP \
+             to state F, it is reported to Josev once the contactor is open"
+        );
+    }
 
     let charge_handler = AfbEvtHandler::new("charge-evt")
         .set_pattern(to_static_str(format!("{}/*", charge_api)))
@@ -874,117 +3000,116 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
         .set_context(shared_context.clone())
         .finalize()?;
 
-    let subscribe_verb = AfbVerb::new("subscribe")
-        .set_callback(on_subscribe)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let mqtt_handler = AfbEvtHandler::new("mqtt-evt")
-        .set_pattern(to_static_str(
-            "from_mqtt/event/iso15118_state_info".to_owned(),
-        ))
-        .set_callback(mqtt_event_cb)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let hlc_charging_handler = AfbEvtHandler::new("hlc-charging-evt")
-        .set_pattern(to_static_str("from_mqtt/event/hlc_charging".to_owned()))
-        .set_callback(on_hlc_charging)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let transaction_status_handler = AfbEvtHandler::new("transaction-status-evt")
-        .set_pattern(to_static_str(
-            "from_mqtt/event/transaction_status".to_owned(),
-        ))
-        .set_callback(on_transaction_status)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let slac_status_handler = AfbEvtHandler::new("slac-status-evt")
-        .set_pattern(to_static_str("from_mqtt/event/slac_status".to_owned()))
-        .set_callback(on_slac_status)
-        .set_context(shared_context.clone())
-        .finalize()?;
+    let iec_handler = match iec_api {
+        Some(iec_api) => Some(
+            AfbEvtHandler::new("iec-evt")
+                .set_pattern(to_static_str(format!("{}/*", iec_api)))
+                .set_callback(iec_event_cb)
+                .set_context(shared_context.clone())
+                .finalize()?,
+        ),
+        None => None,
+    };
 
-    let charge_limit_handler = AfbEvtHandler::new("iso-charge-limit-evt")
-        .set_pattern(to_static_str("from_mqtt/event/iso15118_charge_limit".to_owned()))
-        .set_callback(charge_limit_evt_cb)
-        .set_context(shared_context.clone())
-        .finalize()?;
+    // Josev messages, each one to its consumer
+    let mut josev_handlers = Vec::new();
+    let mut forwarded_events = Vec::new();
+    for (message, consumer) in registry.messages() {
+        let handler = AfbEvtHandler::new(to_static_str(format!("{}-evt", message)))
+            .set_pattern(to_static_str(format!("from_mqtt/event/{}", message)));
+        match consumer {
+            Consumer::Handler(callback) => {
+                handler
+                    .set_callback(*callback)
+                    .set_context(shared_context.clone());
+            }
+            Consumer::Forward => {
+                let event = AfbEvent::new(message);
+                forwarded_events.push(event);
+                handler.set_callback(forward_cb).set_context(ForwardCtx {
+                    shared: shared_context.clone(),
+                    event,
+                });
+            }
+        }
+        josev_handlers.push(handler.finalize()?);
+    }
 
-    let service_status_handler = AfbEvtHandler::new("service-status-evt")
-        .set_pattern(to_static_str("from_mqtt/event/service_status".to_owned()))
-        .set_callback(on_service_status)
-        .set_context(shared_context.clone())
+    let subscribe_josev_verb = AfbVerb::new(SUBSCRIBE_JOSEV_VERB.name)
+        .set_info(SUBSCRIBE_JOSEV_VERB.info)
+        .set_callback(on_subscribe_josev)
+        .set_context(forwarded_events.clone())
         .finalize()?;
 
     //
-    // Verbs called by Josev
+    // Verbs called by Josev, then the others
     //
 
-    let contactor_status_verb = AfbVerb::new("cs_contactor_status")
-        .set_callback(on_contactor_status)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let status_and_limits_verb = AfbVerb::new("cs_status_and_limits")
-        .set_callback(on_status_and_limits)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let cs_parameters_verb = AfbVerb::new("cs_parameters")
-        .set_callback(on_cs_parameters)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let device_model_verb = AfbVerb::new("device_model")
-        .set_callback(on_device_model)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let meter_values_verb = AfbVerb::new("meter_values")
-        .set_callback(on_meter_values)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let stop_charging_verb = AfbVerb::new("stop_charging")
-        .set_callback(on_stop_charging)
-        .set_context(shared_context.clone())
-        .finalize()?;
-
-    let cp_pwm_verb = AfbVerb::new("cp_pwm")
-        .set_callback(on_cp_pwm)
-        .set_context(shared_context.clone())
-        .finalize()?;
+    let mut verbs = Vec::new();
+    for (verb, callback) in JOSEV_VERBS.iter().chain(API_VERBS.iter()) {
+        verbs.push(
+            AfbVerb::new(verb.name)
+                .set_info(verb.info)
+                .set_callback(*callback)
+                .set_context(shared_context.clone())
+                .finalize()?,
+        );
+    }
 
-    // for debugging
-    let force_cp_state_verb = AfbVerb::new("force_cp_state")
-        .set_callback(on_force_cp_state)
-        .set_context(shared_context.clone())
+    let forwarded: Vec<&'static str> = registry
+        .messages()
+        .iter()
+        .filter(|(_, consumer)| matches!(consumer, Consumer::Forward))
+        .map(|(message, _)| *message)
+        .collect();
+    let described: Vec<&VerbDesc> = JOSEV_VERBS
+        .iter()
+        .chain(API_VERBS.iter())
+        .map(|(verb, _)| verb)
+        .chain([&SUBSCRIBE_JOSEV_VERB, &INFO_VERB])
+        .collect();
+    let info = serde_json::to_string(&api_info(JOSEV_API, &described, &forwarded))
+        .or_else(|error| afb_error!(JOSEV_API, "cannot describe the api: {}", error))?;
+    let info_verb = AfbVerb::new(INFO_VERB.name)
+        .set_info(INFO_VERB.info)
+        .set_callback(on_info)
+        .set_context(info)
         .finalize()?;
 
     api.add_evt_handler(charge_handler);
+    if let Some(iec_handler) = iec_handler {
+        api.add_evt_handler(iec_handler);
+    }
     api.add_event(cp_status_event);
     api.add_event(authorization_event);
     api.add_event(contactor_status_event);
     api.add_event(cs_status_and_limits_event);
-    api.add_verb(subscribe_verb);
-    api.add_evt_handler(mqtt_handler);
-    api.add_evt_handler(hlc_charging_handler);
-    api.add_evt_handler(transaction_status_handler);
-    api.add_evt_handler(slac_status_handler);
-    api.add_evt_handler(charge_limit_handler);
-    api.add_evt_handler(service_status_handler);
-    api.add_verb(contactor_status_verb);
-    api.add_verb(status_and_limits_verb);
-    api.add_verb(cs_parameters_verb);
-    api.add_verb(device_model_verb);
-    api.add_verb(meter_values_verb);
-    api.add_verb(stop_charging_verb);
-    api.add_verb(cp_pwm_verb);
-
-    api.add_verb(force_cp_state_verb);
+    for verb in verbs {
+        api.add_verb(verb);
+    }
+    for handler in josev_handlers {
+        api.add_evt_handler(handler);
+    }
+    for event in forwarded_events {
+        api.add_event(event);
+    }
+    api.add_verb(subscribe_josev_verb);
+    api.add_verb(info_verb);
+
+    // Josev reached without afb-mqtt-ext
+    #[cfg(feature = "native-mqtt")]
+    if let Some(mqtt) = jconf.optional::<JsoncObj>("mqtt")? {
+        let ping = watchdog
+            .and_then(|watchdog| watchdog.ping)
+            .filter(|ping| !TO_JOSEV.contains(ping));
+        let requests: Vec<&'static str> = TO_JOSEV.into_iter().chain(ping).collect();
+        crate::bridge::bridge_init(&mqtt, JOSEV_API, &JOSEV_REQUESTS, &josev_messages, &requests)?;
+        api.require_api("from_mqtt");
+    }
+    #[cfg(not(feature = "native-mqtt"))]
+    if jconf.optional::<JsoncObj>("mqtt")?.is_some() {
+        return afb_error!(JOSEV_API, "'mqtt' requires the native-mqtt feature");
+    }
 
     Ok(api.finalize()?)
 }
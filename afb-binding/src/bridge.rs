@@ -0,0 +1,430 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+//
+// Embedded replacement of afb-mqtt-ext: provides the from_mqtt and to_mqtt
+// apis the binding talks to, on top of a native MQTT client
+//
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::mqtt::*;
+use afbv4::prelude::*;
+use serde_json::Value;
+
+const BRIDGE_API: &str = "josev-mqtt";
+
+struct Bridge {
+    config: MqttConfig,
+    link: Option<MqttLink>,
+    // api whose verbs are called on Josev requests, and whose events are sent as updates
+    josev_api: &'static str,
+    // verbs of the josev api Josev may call
+    verbs: Vec<&'static str>,
+    // from_mqtt/event/<name>, by message name
+    events: HashMap<&'static str, &'static AfbEvent>,
+    // to_mqtt requests waiting for Josev, by message id
+    pending: HashMap<String, AfbRequest>,
+    // time given to Josev to answer a to_mqtt request (ms)
+    request_timeout: u32,
+}
+
+type SharedBridge = Arc<Mutex<Bridge>>;
+
+impl Bridge {
+    fn publish(&self, envelope: &Envelope) -> Result<(), AfbError> {
+        match &self.link {
+            Some(link) => link
+                .publish(envelope)
+                .or_else(|error| afb_error!(BRIDGE_API, "{}", error)),
+            None => afb_error!(BRIDGE_API, "not connected, {} dropped", envelope.name),
+        }
+    }
+}
+
+fn to_jsonc(value: &Value) -> Result<JsoncObj, AfbError> {
+    JsoncObj::parse(&value.to_string())
+}
+
+fn from_jsonc(jsonc: &JsoncObj) -> Result<Value, AfbError> {
+    serde_json::from_str(&jsonc.to_string())
+        .or_else(|error| afb_error!(BRIDGE_API, "cannot convert {}: {}", jsonc, error))
+}
+
+struct FromMqttApi {
+    bridge: SharedBridge,
+}
+
+struct IncomingCtx {
+    bridge: SharedBridge,
+    api: AfbApiV4,
+}
+
+impl AfbApiControls for FromMqttApi {
+    fn start(&mut self, api: &AfbApi) -> Result<(), AfbError> {
+        let wakeup_fd = {
+            let mut bridge = self.bridge.lock().unwrap();
+            let link = MqttLink::connect(&bridge.config)
+                .or_else(|error| afb_error!(BRIDGE_API, "{}", error))?;
+            let wakeup_fd = link.wakeup_fd();
+            bridge.link = Some(link);
+            afb_log_msg!(
+                Notice,
+                api.get_apiv4(),
+                "MQTT broker {}:{}",
+                bridge.config.host,
+                bridge.config.port
+            );
+            wakeup_fd
+        };
+
+        // the MQTT thread wakes the event loop up when messages are queued
+        AfbEvtFd::new("josev-mqtt-incoming")
+            .set_fd(wakeup_fd)
+            .set_events(AfbEvtFdPoll::IN)
+            .set_callback(incoming_cb)
+            .set_context(IncomingCtx {
+                bridge: self.bridge.clone(),
+                api: api.get_apiv4(),
+            })
+            .start()?;
+        Ok(())
+    }
+
+    fn exit(&mut self, _api: &AfbApi, code: i32) -> i32 {
+        // disconnects once the josev api said goodbye
+        self.bridge.lock().unwrap().link = None;
+        code
+    }
+
+    // mandatory unsed declaration
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+struct RequestCtx {
+    bridge: SharedBridge,
+    request: Envelope,
+}
+
+// reply of the josev api to a Josev request
+fn josev_reply_cb(api: &AfbApi, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<RequestCtx>()?;
+
+    if args.get_status() < 0 {
+        let error = format!(
+            "{} failed with status {}",
+            ctx.request.name,
+            args.get_status()
+        );
+        afb_log_msg!(Warning, api.get_apiv4(), "Josev request {}", error);
+        let bridge = ctx.bridge.lock().unwrap();
+        return bridge.publish(&Envelope::error(&ctx.request, &error));
+    }
+
+    let data = match args.get::<JsoncObj>(0) {
+        Ok(data) => from_jsonc(&data)?,
+        Err(_) => Value::Null,
+    };
+    let bridge = ctx.bridge.lock().unwrap();
+    bridge.publish(&Envelope::response(&ctx.request, data))
+}
+
+fn on_message(api: AfbApiV4, bridge: &SharedBridge, envelope: Envelope) -> Result<(), AfbError> {
+    match envelope.kind {
+        MessageType::Request => {
+            let (josev_api, verb) = {
+                let bridge = bridge.lock().unwrap();
+                (bridge.josev_api, envelope.verb(&bridge.verbs))
+            };
+            let Some(verb) = verb else {
+                afb_log_msg!(Warning, api, "Unknown Josev request {}", envelope.name);
+                let error = format!("unknown request {}", envelope.name);
+                let bridge = bridge.lock().unwrap();
+                return bridge.publish(&Envelope::error(&envelope, &error));
+            };
+            let data = to_jsonc(&envelope.data)?;
+            AfbSubCall::call_async(
+                api,
+                josev_api,
+                verb,
+                data,
+                josev_reply_cb,
+                RequestCtx {
+                    bridge: bridge.clone(),
+                    request: envelope,
+                },
+            )?;
+        }
+        MessageType::Response => {
+            let pending = bridge.lock().unwrap().pending.remove(&envelope.id);
+            match pending {
+                Some(request) => request.reply(to_jsonc(&envelope.data)?, 0),
+                None => afb_log_msg!(Debug, api, "Late Josev response {}", envelope.name),
+            }
+        }
+        MessageType::Update => {
+            let event = bridge
+                .lock()
+                .unwrap()
+                .events
+                .get(envelope.name.as_str())
+                .copied();
+            match event {
+                Some(event) => {
+                    event.push(to_jsonc(&envelope.data)?);
+                }
+                None => afb_log_msg!(Debug, api, "Josev update {} not subscribed", envelope.name),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn incoming_cb(_fd: &AfbEvtFd, _revents: u32, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<IncomingCtx>()?;
+    let (bridge, api) = (&ctx.bridge, ctx.api);
+
+    // cleared first, a message queued while draining wakes us up again
+    if let Some(link) = &bridge.lock().unwrap().link {
+        link.clear_wakeup();
+    }
+
+    // the lock is not held while a message is processed
+    loop {
+        let received = {
+            let bridge = bridge.lock().unwrap();
+            match &bridge.link {
+                Some(link) => link.try_recv(),
+                None => None,
+            }
+        };
+        match received {
+            Some(Ok(envelope)) => {
+                if let Err(error) = on_message(api, bridge, envelope) {
+                    afb_log_msg!(Warning, api, "{}", error);
+                }
+            }
+            Some(Err(error)) => afb_log_msg!(Warning, api, "MQTT {}", error),
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+//
+// Josev update from the josev api, published as is
+fn josev_event_cb(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let bridge = ctx.get_ref::<SharedBridge>()?;
+    let data = from_jsonc(&args.get::<JsoncObj>(0)?)?;
+
+    let name = evt.get_name();
+    let name = name.rsplit('/').next().unwrap_or(name);
+    let bridge = bridge.lock().unwrap();
+    bridge.publish(&Envelope::update(name, data))
+}
+
+fn on_subscribe_events(
+    request: &AfbRequest,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let bridge = ctx.get_ref::<SharedBridge>()?;
+    let messages = args.get::<JsoncObj>(0)?;
+
+    let bridge = bridge.lock().unwrap();
+    for index in 0..messages.count()? {
+        let message = messages.index::<&str>(index)?;
+        match bridge.events.get(message) {
+            Some(event) => {
+                event.subscribe(request)?;
+            }
+            None => return afb_error!(BRIDGE_API, "unknown Josev message {}", message),
+        }
+    }
+    request.reply(AFB_NO_DATA, 0);
+    Ok(())
+}
+
+fn on_unsubscribe_events(
+    request: &AfbRequest,
+    args: &AfbRqtData,
+    ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let bridge = ctx.get_ref::<SharedBridge>()?;
+    let messages = args.get::<JsoncObj>(0)?;
+
+    let bridge = bridge.lock().unwrap();
+    for index in 0..messages.count()? {
+        let message = messages.index::<&str>(index)?;
+        if let Some(event) = bridge.events.get(message) {
+            event.unsubscribe(request)?;
+        }
+    }
+    request.reply(AFB_NO_DATA, 0);
+    Ok(())
+}
+
+struct ToMqttApi {
+    bridge: SharedBridge,
+}
+
+impl AfbApiControls for ToMqttApi {
+    fn start(&mut self, api: &AfbApi) -> Result<(), AfbError> {
+        // send the events of the josev api as updates
+        let josev_api = self.bridge.lock().unwrap().josev_api;
+        AfbSubCall::call_sync(api, josev_api, "subscribe", AFB_NO_DATA)?;
+        Ok(())
+    }
+
+    // mandatory unsed declaration
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+struct ToMqttCtx {
+    bridge: SharedBridge,
+    name: &'static str,
+}
+
+struct RequestTimeoutCtx {
+    bridge: SharedBridge,
+    id: String,
+}
+
+//
+// Request to Josev, replied once its response arrives
+fn on_to_mqtt(request: &AfbRequest, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<ToMqttCtx>()?;
+    let data = match args.get::<JsoncObj>(0) {
+        Ok(data) => from_jsonc(&data)?,
+        Err(_) => Value::Null,
+    };
+
+    let envelope = Envelope::request(ctx.name, data);
+    let request_timeout = {
+        let mut bridge = ctx.bridge.lock().unwrap();
+        bridge.publish(&envelope)?;
+        bridge
+            .pending
+            .insert(envelope.id.clone(), request.add_ref());
+        bridge.request_timeout
+    };
+
+    AfbTimer::new("josev-mqtt-request")
+        .set_period(request_timeout)
+        .set_decount(1)
+        .set_callback(request_timeout_cb)
+        .set_context(RequestTimeoutCtx {
+            bridge: ctx.bridge.clone(),
+            id: envelope.id,
+        })
+        .start()?;
+    Ok(())
+}
+
+// Josev never answered
+fn request_timeout_cb(_timer: &AfbTimer, _decount: u32, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<RequestTimeoutCtx>()?;
+    let pending = ctx.bridge.lock().unwrap().pending.remove(&ctx.id);
+    if let Some(request) = pending {
+        let error = AfbError::new(BRIDGE_API, -1, "no response from Josev");
+        request.reply(afb_add_trace!(error), -1);
+    }
+    Ok(())
+}
+
+//
+// Create the from_mqtt and to_mqtt apis. `verbs` are the verbs of the josev api
+// Josev may call, `messages` the Josev messages that may be subscribed to,
+// `requests` the verbs of to_mqtt.
+pub fn bridge_init(
+    jconf: &JsoncObj,
+    josev_api: &'static str,
+    verbs: &[&'static str],
+    messages: &[&'static str],
+    requests: &[&'static str],
+) -> Result<(), AfbError> {
+    let config: MqttConfig = serde_json::from_str(&jconf.to_string())
+        .or_else(|error| afb_error!(BRIDGE_API, "'mqtt' malformed: {}", error))?;
+    let request_timeout = jconf.default::<u32>("request_timeout", 500)?;
+
+    let events: HashMap<&'static str, &'static AfbEvent> = messages
+        .iter()
+        .map(|message| {
+            (
+                *message,
+                AfbEvent::new(to_static_str(format!("event/{}", message))),
+            )
+        })
+        .collect();
+
+    let bridge = Arc::new(Mutex::new(Bridge {
+        config,
+        link: None,
+        josev_api,
+        verbs: verbs.to_vec(),
+        events: events.clone(),
+        pending: HashMap::new(),
+        request_timeout,
+    }));
+
+    let from_mqtt = AfbApi::new("from_mqtt").set_callback(Box::new(FromMqttApi {
+        bridge: bridge.clone(),
+    }));
+
+    let subscribe_events_verb = AfbVerb::new("subscribe_events")
+        .set_callback(on_subscribe_events)
+        .set_context(bridge.clone())
+        .finalize()?;
+
+    let unsubscribe_events_verb = AfbVerb::new("unsubscribe_events")
+        .set_callback(on_unsubscribe_events)
+        .set_context(bridge.clone())
+        .finalize()?;
+
+    let josev_handler = AfbEvtHandler::new("josev-mqtt-evt")
+        .set_pattern(to_static_str(format!("{}/*", josev_api)))
+        .set_callback(josev_event_cb)
+        .set_context(bridge.clone())
+        .finalize()?;
+
+    from_mqtt.add_verb(subscribe_events_verb);
+    from_mqtt.add_verb(unsubscribe_events_verb);
+    for event in events.values() {
+        from_mqtt.add_event(event);
+    }
+    from_mqtt.finalize()?;
+
+    let to_mqtt = AfbApi::new("to_mqtt").set_callback(Box::new(ToMqttApi {
+        bridge: bridge.clone(),
+    }));
+    // subscribed to the josev api events once it is started
+    to_mqtt.require_api(josev_api);
+    to_mqtt.add_evt_handler(josev_handler);
+    for name in requests {
+        let verb = AfbVerb::new(name)
+            .set_callback(on_to_mqtt)
+            .set_context(ToMqttCtx {
+                bridge: bridge.clone(),
+                name,
+            })
+            .finalize()?;
+        to_mqtt.add_verb(verb);
+    }
+    to_mqtt.finalize()?;
+
+    Ok(())
+}
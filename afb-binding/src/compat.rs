@@ -0,0 +1,184 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use crate::josev;
+use serde::{Deserialize, Deserializer, Serialize};
+
+//
+// Josev MQTT schema variants: the info of iso15118_state_info messages
+// is serialized differently by the python and rust iso15118 stacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dialect {
+    // python iso15118, camelCase keys
+    Python,
+    // rust iso15118, snake_case keys
+    Rust,
+}
+
+// both spellings of an info key, the one of the dialect first,
+// the python one when the dialect is not known
+fn keys(dialect: Option<Dialect>, python: &'static str, rust: &'static str) -> [&'static str; 2] {
+    match dialect {
+        Some(Dialect::Rust) => [rust, python],
+        _ => [python, rust],
+    }
+}
+
+pub fn payment_option_keys(dialect: Option<Dialect>) -> [&'static str; 2] {
+    keys(dialect, "selectedPaymentOption", "selected_payment_option")
+}
+
+pub fn charging_session_keys(dialect: Option<Dialect>) -> [&'static str; 2] {
+    keys(dialect, "chargingSession", "charging_session")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    // validated with this binding
+    Supported(Option<Dialect>),
+    // known not to work with this binding
+    Unsupported,
+    // not in the table
+    Unknown,
+}
+
+//
+// What to do with an api version missing from the table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownVersionPolicy {
+    Warn,
+    Refuse,
+}
+
+//
+// One entry of the api_versions configuration: a range of api versions of a
+// Josev service the station was validated (or found not to work) with
+#[derive(Debug, Clone, Deserialize)]
+pub struct VersionRange {
+    service: josev::Service,
+    // [from, to[ as "major.minor"
+    #[serde(deserialize_with = "deserialize_version")]
+    from: (u32, u32),
+    #[serde(deserialize_with = "deserialize_version")]
+    to: (u32, u32),
+    supported: bool,
+    // iso15118 only, the schema variant of these versions
+    #[serde(default)]
+    dialect: Option<Dialect>,
+}
+
+fn deserialize_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(u32, u32), D::Error> {
+    let version = String::deserialize(deserializer)?;
+    major_minor(&version)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid api version {}", version)))
+}
+
+// "1.2.3", "v1.2" -> (1, 2)
+fn major_minor(version: &str) -> Option<(u32, u32)> {
+    let mut numbers = version.trim().trim_start_matches('v').split('.');
+    let major = numbers.next()?.parse().ok()?;
+    let minor = match numbers.next() {
+        Some(minor) => minor.parse().ok()?,
+        None => 0,
+    };
+    Some((major, minor))
+}
+
+pub fn check(table: &[VersionRange], service: josev::Service, api_version: &str) -> Compatibility {
+    let Some(version) = major_minor(api_version) else {
+        return Compatibility::Unknown;
+    };
+    table
+        .iter()
+        .find(|range| range.service == service && range.from <= version && version < range.to)
+        .map_or(Compatibility::Unknown, |range| {
+            if range.supported {
+                Compatibility::Supported(range.dialect)
+            } else {
+                Compatibility::Unsupported
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Vec<VersionRange> {
+        serde_json::from_str(
+            r#"[
+                { "service": "iso15118", "from": "0.0", "to": "1.0", "supported": false },
+                { "service": "iso15118", "from": "1.0", "to": "2.0", "supported": true, "dialect": "python" },
+                { "service": "iso15118", "from": "2.0", "to": "3.0", "supported": true, "dialect": "rust" },
+                { "service": "slac", "from": "1.0", "to": "1.4", "supported": true }
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(major_minor("1.2.3"), Some((1, 2)));
+        assert_eq!(major_minor("v1.2"), Some((1, 2)));
+        assert_eq!(major_minor(" 2 "), Some((2, 0)));
+        assert_eq!(major_minor("1.10"), Some((1, 10)));
+        assert_eq!(major_minor(""), None);
+        assert_eq!(major_minor("1.x"), None);
+        assert_eq!(major_minor("latest"), None);
+    }
+
+    #[test]
+    fn compatibility() {
+        use josev::Service::*;
+        let table = table();
+        assert_eq!(check(&table, Iso15118, "0.9.1"), Compatibility::Unsupported);
+        assert_eq!(
+            check(&table, Iso15118, "1.0.0"),
+            Compatibility::Supported(Some(Dialect::Python))
+        );
+        assert_eq!(
+            check(&table, Iso15118, "v2.1"),
+            Compatibility::Supported(Some(Dialect::Rust))
+        );
+        assert_eq!(check(&table, Iso15118, "3.0"), Compatibility::Unknown);
+        assert_eq!(check(&table, Slac, "1.3.9"), Compatibility::Supported(None));
+        // [from, to[
+        assert_eq!(check(&table, Slac, "1.4"), Compatibility::Unknown);
+        assert_eq!(check(&table, Ocpp, "1.0"), Compatibility::Unknown);
+        assert_eq!(check(&table, Iso15118, "unknown"), Compatibility::Unknown);
+        assert_eq!(check(&[], Iso15118, "1.0"), Compatibility::Unknown);
+    }
+
+    #[test]
+    fn invalid_range() {
+        let range = r#"[{ "service": "slac", "from": "one", "to": "2.0", "supported": true }]"#;
+        assert!(serde_json::from_str::<Vec<VersionRange>>(range).is_err());
+    }
+
+    #[test]
+    fn dialect_keys() {
+        assert_eq!(
+            payment_option_keys(None),
+            ["selectedPaymentOption", "selected_payment_option"]
+        );
+        assert_eq!(
+            payment_option_keys(Some(Dialect::Rust)),
+            ["selected_payment_option", "selectedPaymentOption"]
+        );
+        assert_eq!(
+            charging_session_keys(Some(Dialect::Python)),
+            ["chargingSession", "charging_session"]
+        );
+    }
+}
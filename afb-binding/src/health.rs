@@ -0,0 +1,92 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use std::collections::HashMap;
+
+use crate::josev;
+use afbv4::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+//
+// Last known status of a Josev service
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ServiceHealth {
+    pub service: josev::Service,
+    pub status: josev::ServiceStatusStatus,
+    pub software_version: String,
+    pub api_version: String,
+    // when the current status was entered
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(schema_with = "josev::rfc3339_schema")]
+    pub since: OffsetDateTime,
+    // number of times the service became ready
+    pub starts: u32,
+}
+
+AfbDataConverter!(services_health, ServicesHealth);
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ServicesHealth {
+    pub services: Vec<ServiceHealth>,
+}
+
+//
+// Health of every Josev service seen so far
+#[derive(Default)]
+pub struct Services {
+    services: HashMap<josev::Service, ServiceHealth>,
+}
+
+impl Services {
+    //
+    // Record a service_status update, returns true when the service
+    // (re)started, i.e. became ready
+    pub fn update(&mut self, msg: &josev::ServiceStatusUpdate) -> bool {
+        let now = OffsetDateTime::now_utc();
+        let health = self
+            .services
+            .entry(msg.service)
+            .or_insert_with(|| ServiceHealth {
+                service: msg.service,
+                status: msg.status,
+                software_version: msg.software_version.clone(),
+                api_version: msg.api_version.clone(),
+                since: now,
+                starts: 0,
+            });
+
+        let started = msg.status == josev::ServiceStatusStatus::Ready
+            && (health.starts == 0 || health.status != josev::ServiceStatusStatus::Ready);
+        if health.status != msg.status {
+            health.since = now;
+        }
+        if started {
+            health.starts += 1;
+        }
+        health.status = msg.status;
+        health.software_version = msg.software_version.clone();
+        health.api_version = msg.api_version.clone();
+        started
+    }
+
+    pub fn status(&self) -> ServicesHealth {
+        let mut services: Vec<ServiceHealth> = self.services.values().cloned().collect();
+        services.sort_by_key(|health| health.service.to_string());
+        ServicesHealth { services }
+    }
+}
+
+pub fn health_registers() -> Result<(), AfbError> {
+    services_health::register()?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use crate::josev;
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+use serde::Serialize;
+
+pub type SchemaFn = fn() -> RootSchema;
+
+pub fn schema<T: JsonSchema>() -> RootSchema {
+    schema_for!(T)
+}
+
+//
+// Verb declaration, kept in the verb tables of the binding
+pub struct VerbDesc {
+    pub name: &'static str,
+    pub info: &'static str,
+    pub request: Option<SchemaFn>,
+    pub response: Option<SchemaFn>,
+}
+
+impl VerbDesc {
+    pub const fn new(name: &'static str, info: &'static str) -> Self {
+        VerbDesc {
+            name,
+            info,
+            request: None,
+            response: None,
+        }
+    }
+
+    pub const fn request(self, request: SchemaFn) -> Self {
+        VerbDesc {
+            request: Some(request),
+            ..self
+        }
+    }
+
+    pub const fn response(self, response: SchemaFn) -> Self {
+        VerbDesc {
+            response: Some(response),
+            ..self
+        }
+    }
+}
+
+//
+// Description of a verb, with the JSON Schemas of its argument and reply
+#[derive(Debug, Serialize)]
+pub struct VerbInfo {
+    pub name: &'static str,
+    pub info: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<RootSchema>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<RootSchema>,
+}
+
+impl From<&VerbDesc> for VerbInfo {
+    fn from(verb: &VerbDesc) -> Self {
+        VerbInfo {
+            name: verb.name,
+            info: verb.info,
+            request: verb.request.map(|schema| schema()),
+            response: verb.response.map(|schema| schema()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventInfo {
+    pub name: String,
+    pub info: String,
+    // none when the payload is not checked by the binding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<RootSchema>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiInfo {
+    pub api: &'static str,
+    pub info: &'static str,
+    pub verbs: Vec<VerbInfo>,
+    pub events: Vec<EventInfo>,
+}
+
+fn events(forwarded: &[&'static str]) -> Vec<EventInfo> {
+    let mut events = vec![
+        EventInfo {
+            name: "cp_status".to_string(),
+            info: "Control pilot state, sent to Josev".to_string(),
+            data: Some(schema_for!(josev::CpStatusUpdate)),
+        },
+        EventInfo {
+            name: "authorization".to_string(),
+            info: "EIM authorization result, sent to Josev".to_string(),
+            data: Some(schema_for!(josev::AuthorizationUpdate)),
+        },
+        EventInfo {
+            name: "cs_contactor_status".to_string(),
+            info: "Contactor state, sent to Josev".to_string(),
+            data: Some(schema_for!(josev::CsContactorStatusUpdate)),
+        },
+        EventInfo {
+            name: "cs_status_and_limits".to_string(),
+            info: "Status and limits of the EVSEs, sent to Josev".to_string(),
+            data: Some(schema_for!(josev::CsStatusAndLimitsResponse)),
+        },
+    ];
+    for message in forwarded {
+        events.push(EventInfo {
+            name: message.to_string(),
+            info: format!("Josev {} update, forwarded as is", message),
+            data: None,
+        });
+    }
+    events
+}
+
+pub fn api_info(api: &'static str, verbs: &[&VerbDesc], forwarded: &[&'static str]) -> ApiInfo {
+    ApiInfo {
+        api,
+        info: "ISO 15118 charging through Josev",
+        verbs: verbs.iter().map(|verb| VerbInfo::from(*verb)).collect(),
+        events: events(forwarded),
+    }
+}
@@ -1,10 +1,20 @@
 use afbv4::prelude::*;
-use serde::{Deserialize, Serialize};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 use strum_macros::{Display, EnumString, IntoStaticStr};
 use time::OffsetDateTime;
 
+// timestamps are RFC 3339 strings
+pub fn rfc3339_schema(gen: &mut SchemaGenerator) -> Schema {
+    let mut schema = String::json_schema(gen).into_object();
+    schema.format = Some("date-time".to_string());
+    schema.into()
+}
+
 AfbDataConverter!(authorization_update, AuthorizationUpdate);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct AuthorizationUpdate {
     pub evse_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -13,7 +23,7 @@ pub struct AuthorizationUpdate {
     pub status: AuthorizationStatus,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 pub enum AuthorizationTokenType {
     ISO14443,
     ISO15693,
@@ -37,7 +47,7 @@ pub enum AuthorizationTokenType {
     Central,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum AuthorizationStatus {
@@ -47,7 +57,7 @@ pub enum AuthorizationStatus {
 }
 
 AfbDataConverter!(authorization_request, AuthorizationRequest);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct AuthorizationRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub evse_id: Option<String>,
@@ -57,12 +67,12 @@ pub struct AuthorizationRequest {
 }
 
 AfbDataConverter!(authorization_response, AuthorizationResponse);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct AuthorizationResponse {
     pub status: AuthorizationStatus,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, IntoStaticStr)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, IntoStaticStr)]
 #[serde(tag = "type", content = "data", rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CableCheck {
@@ -70,7 +80,7 @@ pub enum CableCheck {
     Response(CableCheckResponse),
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CableCheckAction {
@@ -78,7 +88,7 @@ pub enum CableCheckAction {
     Status,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CableCheckStatus {
@@ -86,7 +96,7 @@ pub enum CableCheckStatus {
     Finished,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum IsolationLevel {
@@ -94,18 +104,20 @@ pub enum IsolationLevel {
     Invalid,
     Warning,
     Fault,
+    #[serde(rename = "no_imd")]
+    #[strum(serialize = "no_imd")]
     NoIMD,
 }
 
 AfbDataConverter!(cable_check_request, CableCheckRequest);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CableCheckRequest {
     pub evse_id: String,
     pub cable_check_action: CableCheckAction,
 }
 
 AfbDataConverter!(cable_check_response, CableCheckResponse);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CableCheckResponse {
     pub evse_id: String,
     pub cable_check_status: CableCheckStatus,
@@ -114,17 +126,25 @@ pub struct CableCheckResponse {
 }
 
 AfbDataConverter!(cp_status_update, CpStatusUpdate);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CpStatusUpdate {
     pub evse_id: String,
     pub connector_id: u32,
     pub state: ControlPilotState,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_voltage: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub min_voltage: Option<f32>,
-    pub duty_cycle: Option<f32>, // TODO: constraints min 0 max 100
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        deserialize_with = "deserialize_duty_cycle"
+    )]
+    #[schemars(range(min = 0, max = 100))]
+    pub duty_cycle: Option<f32>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 pub enum ControlPilotState {
     A1,
     A2,
@@ -139,13 +159,13 @@ pub enum ControlPilotState {
 }
 
 AfbDataConverter!(cs_contactor_status_request, CsContactorStatusRequest);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsContactorStatusRequest {
     pub evse_id: String,
 }
 
 AfbDataConverter!(cs_contactor_status_response, CsContactorStatusResponse);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsContactorStatusResponse {
     pub evse_id: String,
     pub status: CsContactorStatusResponseStatus,
@@ -153,7 +173,7 @@ pub struct CsContactorStatusResponse {
     pub info: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CsContactorStatusResponseStatus {
@@ -163,14 +183,14 @@ pub enum CsContactorStatusResponseStatus {
 }
 
 AfbDataConverter!(cs_contactor_status_update, CsContactorStatusUpdate);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsContactorStatusUpdate {
     pub evse_id: String,
     pub status: CsContactorStatusResponseStatus,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub info: Option<String>,
 }
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct AcSetpoint {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub charge_active_power: Option<f32>,
@@ -186,7 +206,7 @@ pub struct AcSetpoint {
     pub discharge_current: Option<f32>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DcSetpoint {
     pub voltage: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -199,7 +219,7 @@ pub struct DcSetpoint {
     pub discharge_power: Option<f32>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct EvLimits {
     pub maximum_voltage: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -210,7 +230,7 @@ AfbDataConverter!(
     power_electronics_setpoint_request,
     PowerElectronicsSetpointRequest
 );
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct PowerElectronicsSetpointRequest {
     pub evse_id: String,
     pub ev_limits: EvLimits,
@@ -222,7 +242,7 @@ pub struct PowerElectronicsSetpointRequest {
     pub dc: Option<DcSetpoint>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum SetPointRequestStatus {
@@ -234,19 +254,19 @@ AfbDataConverter!(
     power_electronics_setpoint_response,
     PowerElectronicsSetpointResponse
 );
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct PowerElectronicsSetpointResponse {
     pub evse_id: String,
     pub status: SetPointRequestStatus,
 }
 
 AfbDataConverter!(cs_status_and_limits_response, CsStatusAndLimitsResponse);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsStatusAndLimitsResponse {
     pub evses: Vec<CsStatusAndLimitsEvse>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsStatusAndLimitsEvse {
     pub evse_id: String,
     pub status_code: CsStatusAndLimitsStatusCode,
@@ -260,34 +280,34 @@ pub struct CsStatusAndLimitsEvse {
     pub dc_bpt: Option<CsStatusAndLimitsDcBpt>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsStatusAndLimitsAc {
     pub max_current: AcMaxCurrent,
     pub nominal_voltage: f32,
     pub rcd_error: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsStatusAndLimitsAcBpt {
     pub evse_max_discharge_power: AcPowerLimit,
     pub evse_min_discharge_power: AcPowerLimit,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct AcMaxCurrent {
     pub l1: f32,
     pub l2: f32,
     pub l3: f32,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct AcPowerLimit {
     pub l1: f32,
     pub l2: f32,
     pub l3: f32,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsStatusAndLimitsDc {
     pub present_voltage: f32,
     pub present_current: f32,
@@ -307,7 +327,7 @@ pub struct CsStatusAndLimitsDc {
     pub evse_power_ramp_limit: Option<f32>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsStatusAndLimitsDcBpt {
     pub evse_max_discharge_power: f32,
     pub evse_min_discharge_power: f32,
@@ -315,7 +335,7 @@ pub struct CsStatusAndLimitsDcBpt {
     pub evse_min_discharge_current: f32,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CsStatusAndLimitsDcIsolation {
@@ -326,7 +346,7 @@ pub enum CsStatusAndLimitsDcIsolation {
     NoImd,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CsStatusAndLimitsStatusCode {
@@ -340,7 +360,7 @@ pub enum CsStatusAndLimitsStatusCode {
 }
 
 AfbDataConverter!(cs_parameters_response, CsParametersResponse);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsParametersResponse {
     pub sw_version: String,
     pub hw_version: String,
@@ -348,7 +368,7 @@ pub struct CsParametersResponse {
     pub parameters: Vec<CsParametersResponseEntry>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsParametersResponseEntry {
     pub evse_id: String,
     pub supports_eim: bool,
@@ -356,13 +376,13 @@ pub struct CsParametersResponseEntry {
     pub connectors: Vec<CsParametersConnector>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsParametersConnector {
     pub id: u32,
     pub services: CsParametersConnectorService,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsParametersConnectorService {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ac: Option<CsParametersConnectorServiceAc>,
@@ -374,7 +394,7 @@ pub struct CsParametersConnectorService {
     pub dc_bpt: Option<CsParametersConnectorServiceDcBpt>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 pub enum CsConnectorType {
     #[serde(rename = "AC_single_phase_core")]
     #[strum(serialize = "AC_single_phase_core")]
@@ -396,21 +416,21 @@ pub enum CsConnectorType {
     DCUnique,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsParametersConnectorServiceCommon {
     pub connector_type: CsConnectorType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub control_mode: Option<CsParametersControlMode>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsParametersConnectorServiceAc {
     #[serde(flatten)]
     pub common: CsParametersConnectorServiceCommon,
     pub nominal_voltage: u32,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsParametersConnectorServiceAcBpt {
     #[serde(flatten)]
     pub common: CsParametersConnectorServiceCommon,
@@ -423,7 +443,7 @@ pub struct CsParametersConnectorServiceAcBpt {
     pub grid_island_detection_mode: Option<CsParametersGridCodeIslandingDetectionMode>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsParametersConnectorServiceDc {
     #[serde(flatten)]
     pub common: CsParametersConnectorServiceCommon,
@@ -431,7 +451,7 @@ pub struct CsParametersConnectorServiceDc {
     pub nominal_voltage: Option<u32>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CsParametersConnectorServiceDcBpt {
     #[serde(flatten)]
     pub common: CsParametersConnectorServiceCommon,
@@ -445,7 +465,7 @@ pub struct CsParametersConnectorServiceDcBpt {
     pub grid_island_detection_mode: Option<CsParametersGridCodeIslandingDetectionMode>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CsParametersControlMode {
@@ -453,7 +473,7 @@ pub enum CsParametersControlMode {
     Dynamic,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CsParametersBptChannel {
@@ -461,7 +481,7 @@ pub enum CsParametersBptChannel {
     Separated,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CsParametersGeneratorMode {
@@ -469,7 +489,7 @@ pub enum CsParametersGeneratorMode {
     GridForming,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CsParametersGridCodeIslandingDetectionMode {
@@ -477,21 +497,21 @@ pub enum CsParametersGridCodeIslandingDetectionMode {
     Passive,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct MeterValuesUpdateVoltage {
     pub l1: f32,
     pub l2: f32,
     pub l3: f32,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct MeterValuesUpdateCurrent {
     pub l1: f32,
     pub l2: f32,
     pub l3: f32,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct SignedMeterValuesUpdate {
     pub measurand: SignedMeterValuesMeasurand,
     pub signed_meter_data: String,
@@ -500,7 +520,7 @@ pub struct SignedMeterValuesUpdate {
     pub public_key: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, IntoStaticStr, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, IntoStaticStr, Copy)]
 pub enum SignedMeterValuesMeasurand {
     #[serde(rename = "Current.Export")]
     CurrentExport,
@@ -553,24 +573,28 @@ pub enum SignedMeterValuesMeasurand {
 }
 
 AfbDataConverter!(meter_values_request, MeterValuesRequest);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct MeterValuesRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub evse_id: Option<String>,
     #[serde(with = "time::serde::rfc3339")]
+    #[schemars(schema_with = "rfc3339_schema")]
     pub timestamp: OffsetDateTime,
 }
 
 AfbDataConverter!(meter_values_response, MeterValuesResponse);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct MeterValuesResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub evse_id: Option<String>,
     #[serde(with = "time::serde::rfc3339")]
+    #[schemars(schema_with = "rfc3339_schema")]
     pub timestamp: OffsetDateTime,
     pub voltage: MeterValuesUpdateVoltage,
     pub current: MeterValuesUpdateCurrent,
-    pub power_factor: f32, // TODO: 0 <= x <= 1
+    #[serde(deserialize_with = "deserialize_power_factor")]
+    #[schemars(range(min = 0, max = 1))]
+    pub power_factor: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dc_current: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -588,7 +612,7 @@ pub struct MeterValuesResponse {
     pub signed_meter_values: Option<Vec<SignedMeterValuesUpdate>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum MessageStatus {
@@ -597,27 +621,44 @@ pub enum MessageStatus {
 }
 
 AfbDataConverter!(stop_charging_request, StopChargingRequest);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StopChargingRequest {
     pub evse_id: String,
 }
 
 AfbDataConverter!(stop_charging_response, StopChargingResponse);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct StopChargingResponse {
     pub evse_id: String,
     pub status: MessageStatus,
 }
 
+AfbDataConverter!(connector_lock_request, ConnectorLockRequest);
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct ConnectorLockRequest {
+    pub evse_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connector_id: Option<u32>,
+}
+
+AfbDataConverter!(connector_lock_response, ConnectorLockResponse);
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct ConnectorLockResponse {
+    pub evse_id: String,
+    pub status: MessageStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+}
+
 AfbDataConverter!(slac_status_update, SlacStatusUpdate);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct SlacStatusUpdate {
     pub evse_id: String,
     pub run_id: String,
     pub status: SlacStatusUpdateStatus,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum SlacStatusUpdateStatus {
@@ -628,18 +669,121 @@ pub enum SlacStatusUpdateStatus {
     BasicCharging,
 }
 
+AfbDataConverter!(change_availability_request, ChangeAvailabilityRequest);
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct ChangeAvailabilityRequest {
+    pub evse_id: String,
+    pub operational_status: OperationalStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum OperationalStatus {
+    Operative,
+    Inoperative,
+}
+
+AfbDataConverter!(change_availability_response, ChangeAvailabilityResponse);
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct ChangeAvailabilityResponse {
+    pub evse_id: String,
+    pub status: ChangeAvailabilityStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ChangeAvailabilityStatus {
+    Accepted,
+    Rejected,
+    // applied once the ongoing session is over
+    Scheduled,
+}
+
+AfbDataConverter!(hlc_charging_update, HlcChargingUpdate);
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct HlcChargingUpdate {
+    pub evse_id: String,
+    pub status: bool,
+}
+
+AfbDataConverter!(transaction_status_update, TransactionStatusUpdate);
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct TransactionStatusUpdate {
+    pub evse_id: String,
+    pub status: TransactionStatus,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub transaction_id: Option<String>,
+    // only on ended transactions
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub stop_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TransactionStatus {
+    Started,
+    Updated,
+    Ended,
+}
+
+AfbDataConverter!(charge_limit_update, ChargeLimitUpdate);
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct ChargeLimitUpdate {
+    pub evse_id: String,
+    // negative for discharge (ISO 15118-20 BPT)
+    pub limit: f64,
+}
+
 AfbDataConverter!(cp_pwm_request, CpPwmRequest);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CpPwmRequest {
     pub evse_id: String,
     pub hlc: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub current: Option<f32>, // TODO: constrain minimum 0, precision 1.d.p
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        deserialize_with = "deserialize_current"
+    )]
+    #[schemars(range(min = 0))]
+    pub current: Option<f32>,
     pub error_state: bool,
     pub fault_state: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+// amperes, with a precision of one decimal
+fn deserialize_current<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f32>, D::Error> {
+    let current = Option::<f32>::deserialize(deserializer)?;
+    current
+        .map(|current| in_range("current", current, 0.0, f32::INFINITY))
+        .transpose()
+        .map(|current| current.map(|current| (current * 10.0).round() / 10.0))
+}
+
+// percent
+fn deserialize_duty_cycle<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f32>, D::Error> {
+    let duty_cycle = Option::<f32>::deserialize(deserializer)?;
+    duty_cycle
+        .map(|duty_cycle| in_range("duty_cycle", duty_cycle, 0.0, 100.0))
+        .transpose()
+}
+
+fn deserialize_power_factor<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    in_range("power_factor", f32::deserialize(deserializer)?, 0.0, 1.0)
+}
+
+// the ranges of the schemas, enforced when parsing
+fn in_range<E: serde::de::Error>(name: &str, value: f32, min: f32, max: f32) -> Result<f32, E> {
+    if (min..=max).contains(&value) {
+        Ok(value)
+    } else {
+        Err(E::custom(format!("{} {} out of range [{}, {}]", name, value, min, max)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum CpPwmResponseStatus {
@@ -649,7 +793,7 @@ pub enum CpPwmResponseStatus {
 }
 
 AfbDataConverter!(cp_pwm_response, CpPwmResponse);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct CpPwmResponse {
     pub evse_id: String,
     pub status: CpPwmResponseStatus,
@@ -658,7 +802,7 @@ pub struct CpPwmResponse {
 }
 
 AfbDataConverter!(device_model_response, DeviceModelResponse);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DeviceModelResponse {
     pub model: String,
     pub vendor: String,
@@ -673,11 +817,11 @@ pub struct DeviceModelResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sim_imsi: Option<String>,
     pub evses: Vec<DeviceModelEvse>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default = "Vec::new")]
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub components: Vec<DeviceModelComponent>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DeviceModelEvse {
     pub ocpp_id: u32,
     pub iso15118_id: String,
@@ -686,13 +830,13 @@ pub struct DeviceModelEvse {
     pub connectors: Vec<DeviceModelEvseConnector>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DeviceModelEvseConnector {
     pub id: u32,
     pub connector_type: DeviceModelEvseConnectorType,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 pub enum DeviceModelEvseConnectorType {
     #[serde(rename = "cCCS1")]
     #[strum(serialize = "cCCS1")]
@@ -750,7 +894,7 @@ pub enum DeviceModelEvseConnectorType {
     Unknown,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DeviceModelComponent {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -759,11 +903,11 @@ pub struct DeviceModelComponent {
     pub evse_id: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub connector_id: Option<u32>,
-    #[serde(skip_serializing_if = "Vec::is_empty", default = "Vec::new")]
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub variables: Vec<DeviceModelComponentVariable>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct DeviceModelComponentVariable {
     pub name: String,
     pub value: String,
@@ -781,14 +925,14 @@ pub struct DeviceModelComponentVariable {
     pub values_list: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 pub enum DeviceModelComponentVariableMutability {
     ReadOnly,
     ReadWrite,
     WriteOnly,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 pub enum DataType {
     #[serde(rename = "string")]
     String,
@@ -805,7 +949,7 @@ pub enum DataType {
     MemberList,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum ServiceStatusStatus {
@@ -816,7 +960,7 @@ pub enum ServiceStatusStatus {
     Busy,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, EnumString, Hash, Eq, Display, Copy)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema, EnumString, Hash, Eq, Display, Copy)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum Service {
@@ -829,7 +973,7 @@ pub enum Service {
 
 
 AfbDataConverter!(service_status_update, ServiceStatusUpdate);
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
 pub struct ServiceStatusUpdate {
     pub service: Service,
     pub software_version: String,
@@ -857,7 +1001,14 @@ pub fn josev_registers() -> Result<(), AfbError> {
     meter_values_response::register()?;
     stop_charging_request::register()?;
     stop_charging_response::register()?;
+    connector_lock_request::register()?;
+    connector_lock_response::register()?;
     slac_status_update::register()?;
+    change_availability_request::register()?;
+    change_availability_response::register()?;
+    hlc_charging_update::register()?;
+    transaction_status_update::register()?;
+    charge_limit_update::register()?;
     cp_pwm_request::register()?;
     cp_pwm_response::register()?;
     device_model_response::register()?;
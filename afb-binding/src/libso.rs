@@ -22,4 +22,17 @@ extern crate afbv4;
 #[path="josev_types.rs"]
 mod josev;
 
-mod binding;
\ No newline at end of file
+mod args;
+mod binding;
+#[cfg(feature = "native-mqtt")]
+mod bridge;
+mod compat;
+mod health;
+mod info;
+mod messages;
+#[cfg(feature = "native-mqtt")]
+mod mqtt;
+mod mqtt_config;
+mod registry;
+mod session;
+mod subcall;
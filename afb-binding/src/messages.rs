@@ -0,0 +1,56 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+//
+// Names of the messages exchanged with Josev, and its MQTT topics.
+// The verb and event tables of the binding follow them, and the
+// afb-mqtt-ext configuration is generated from them.
+//
+
+// messages to Josev
+pub const PUBLISH_TOPIC: &str = "cs/josev";
+
+// messages from Josev
+pub const SUBSCRIBE_TOPICS: [&str; 2] = ["josev/cs", "smart_charging/iso15118"];
+
+// Requests from Josev, each one a verb of the binding
+pub const JOSEV_REQUESTS: [&str; 8] = [
+    "cs_contactor_status",
+    "cs_status_and_limits",
+    "cs_parameters",
+    "device_model",
+    "meter_values",
+    "stop_charging",
+    "cp_pwm",
+    "change_availability",
+];
+
+// Updates sent to Josev, events pushed through the subscribe verb
+pub const JOSEV_EVENTS: [&str; 4] = [
+    "cp_status",
+    "authorization",
+    "cs_contactor_status",
+    "cs_status_and_limits",
+];
+
+// Requests sent to Josev through to_mqtt, besides the watchdog ping
+pub const TO_JOSEV: [&str; 1] = ["authorization"];
+
+// Josev updates handled by the binding, the configuration may add more
+pub const JOSEV_MESSAGES: [&str; 6] = [
+    "iso15118_state_info",
+    "iso15118_charge_limit",
+    "hlc_charging",
+    "transaction_status",
+    "slac_status",
+    "service_status",
+];
@@ -0,0 +1,289 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+//
+// MQTT transport of the Josev messages, independent of AFB
+//
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageType {
+    Request,
+    Response,
+    Update,
+}
+
+//
+// Envelope of every Josev MQTT message
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Envelope {
+    // correlates a response with its request
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: MessageType,
+    #[serde(default)]
+    pub data: Value,
+}
+
+impl Envelope {
+    pub fn request(name: &str, data: Value) -> Self {
+        Envelope {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            kind: MessageType::Request,
+            data,
+        }
+    }
+
+    pub fn response(request: &Envelope, data: Value) -> Self {
+        Envelope {
+            id: request.id.clone(),
+            name: request.name.clone(),
+            kind: MessageType::Response,
+            data,
+        }
+    }
+
+    pub fn update(name: &str, data: Value) -> Self {
+        Envelope {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            kind: MessageType::Update,
+            data,
+        }
+    }
+
+    // response telling Josev its request failed
+    pub fn error(request: &Envelope, error: &str) -> Self {
+        Envelope::response(request, json!({ "error": error }))
+    }
+
+    // the verb answering a request, when it is one of `verbs`
+    pub fn verb(&self, verbs: &[&'static str]) -> Option<&'static str> {
+        verbs.iter().copied().find(|verb| *verb == self.name)
+    }
+}
+
+//
+// Broker and topics, the defaults are the ones of Josev
+#[derive(Debug, Clone, Deserialize)]
+pub struct MqttConfig {
+    #[serde(default = "MqttConfig::default_host")]
+    pub host: String,
+    #[serde(default = "MqttConfig::default_port")]
+    pub port: u16,
+    #[serde(default = "MqttConfig::default_client_id")]
+    pub client_id: String,
+    // messages to Josev
+    #[serde(default = "MqttConfig::default_publish_topic")]
+    pub publish_topic: String,
+    // messages from Josev
+    #[serde(default = "MqttConfig::default_subscribe_topics")]
+    pub subscribe_topics: Vec<String>,
+    // seconds
+    #[serde(default = "MqttConfig::default_keep_alive")]
+    pub keep_alive: u64,
+}
+
+impl MqttConfig {
+    fn default_host() -> String {
+        "localhost".to_string()
+    }
+
+    fn default_port() -> u16 {
+        1883
+    }
+
+    fn default_client_id() -> String {
+        "afb-josev".to_string()
+    }
+
+    fn default_publish_topic() -> String {
+        "cs/josev".to_string()
+    }
+
+    fn default_subscribe_topics() -> Vec<String> {
+        vec![
+            "josev/cs".to_string(),
+            "smart_charging/iso15118".to_string(),
+        ]
+    }
+
+    fn default_keep_alive() -> u64 {
+        10
+    }
+}
+
+#[derive(Debug)]
+pub struct MqttError(pub String);
+
+impl fmt::Display for MqttError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+//
+// Connection to the broker. The network is handled by a thread of its own that
+// reconnects when needed, received messages (or errors) are queued for the owner,
+// and the wakeup socket made readable.
+pub struct MqttLink {
+    client: Client,
+    publish_topic: String,
+    incoming: Receiver<Result<Envelope, MqttError>>,
+    wakeup: UnixStream,
+    closing: Arc<AtomicBool>,
+}
+
+impl MqttLink {
+    pub fn connect(config: &MqttConfig) -> Result<Self, MqttError> {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(config.keep_alive));
+        let (client, mut connection) = Client::new(options, 64);
+
+        let (sender, incoming) = mpsc::channel();
+        let closing = Arc::new(AtomicBool::new(false));
+
+        let (wakeup, waker) = UnixStream::pair()
+            .and_then(|(wakeup, waker)| {
+                wakeup.set_nonblocking(true)?;
+                waker.set_nonblocking(true)?;
+                Ok((wakeup, waker))
+            })
+            .map_err(|error| MqttError(format!("cannot create wakeup socket: {}", error)))?;
+
+        let subscriber = client.clone();
+        let topics = config.subscribe_topics.clone();
+        let stop = closing.clone();
+        thread::Builder::new()
+            .name("josev-mqtt".to_string())
+            .spawn(move || {
+                // only report the first error of a disconnection
+                let mut connected = true;
+                for notification in connection.iter() {
+                    let queued = match notification {
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            // subscriptions do not survive a reconnection, the
+                            // event loop runs here hence the non blocking calls
+                            connected = true;
+                            let subscribed = topics.iter().try_for_each(|topic| {
+                                subscriber.try_subscribe(topic, QoS::AtLeastOnce)
+                            });
+                            match subscribed {
+                                Ok(()) => Ok(false),
+                                Err(error) => sender
+                                    .send(Err(MqttError(format!("cannot subscribe: {}", error))))
+                                    .map(|_| true),
+                            }
+                        }
+                        Ok(Event::Incoming(Packet::Publish(publish))) => {
+                            let envelope = serde_json::from_slice::<Envelope>(&publish.payload)
+                                .map_err(|error| {
+                                    MqttError(format!(
+                                        "malformed message on {}: {}",
+                                        publish.topic, error
+                                    ))
+                                });
+                            sender.send(envelope).map(|_| true)
+                        }
+                        Ok(_) => Ok(false),
+                        Err(_) if stop.load(Ordering::Relaxed) => break,
+                        Err(error) => {
+                            let queued = if connected {
+                                connected = false;
+                                sender
+                                    .send(Err(MqttError(format!("broker connection: {}", error))))
+                                    .map(|_| true)
+                            } else {
+                                Ok(false)
+                            };
+                            // the next iteration reconnects
+                            thread::sleep(Duration::from_secs(1));
+                            queued
+                        }
+                    };
+                    match queued {
+                        // a full socket already wakes the owner up
+                        Ok(true) => {
+                            let _ = (&waker).write(&[1]);
+                        }
+                        Ok(false) => {}
+                        // nobody listens anymore
+                        Err(_) => break,
+                    }
+                }
+            })
+            .map_err(|error| MqttError(format!("cannot start mqtt thread: {}", error)))?;
+
+        Ok(MqttLink {
+            client,
+            publish_topic: config.publish_topic.clone(),
+            incoming,
+            wakeup,
+            closing,
+        })
+    }
+
+    // never blocks, fails when the broker is unreachable for too long
+    pub fn publish(&self, envelope: &Envelope) -> Result<(), MqttError> {
+        let payload = serde_json::to_vec(envelope)
+            .map_err(|error| MqttError(format!("cannot serialize {}: {}", envelope.name, error)))?;
+        self.client
+            .try_publish(&self.publish_topic, QoS::AtLeastOnce, false, payload)
+            .map_err(|error| MqttError(format!("cannot publish {}: {}", envelope.name, error)))
+    }
+
+    pub fn try_recv(&self) -> Option<Result<Envelope, MqttError>> {
+        self.incoming.try_recv().ok()
+    }
+
+    // readable when messages are queued, to be watched by the owner event loop
+    pub fn wakeup_fd(&self) -> RawFd {
+        self.wakeup.as_raw_fd()
+    }
+
+    // to be called before draining the queue, returns false when nothing woke us up
+    pub fn clear_wakeup(&self) -> bool {
+        let mut woken = false;
+        let mut buffer = [0u8; 64];
+        while let Ok(count) = (&self.wakeup).read(&mut buffer) {
+            if count == 0 {
+                break;
+            }
+            woken = true;
+        }
+        woken
+    }
+}
+
+impl Drop for MqttLink {
+    fn drop(&mut self) {
+        self.closing.store(true, Ordering::Relaxed);
+        let _ = self.client.try_disconnect();
+    }
+}
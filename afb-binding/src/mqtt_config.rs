@@ -0,0 +1,150 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+//
+// afb-mqtt-ext configuration (mqtt-config.yml), generated from what the binding declares
+//
+
+use std::fmt::Write;
+
+use crate::messages::{PUBLISH_TOPIC, SUBSCRIBE_TOPICS};
+
+pub struct MqttMapping<'a> {
+    // the binding api, called on Josev requests
+    pub api: &'a str,
+    // verb the extension calls to receive the events sent to Josev
+    pub subscribe_verb: &'a str,
+    // verbs called by Josev
+    pub requests: &'a [&'a str],
+    // requests sent to Josev, verbs of to_mqtt
+    pub to_josev: &'a [&'a str],
+    // events sent to Josev as updates
+    pub events: &'a [&'a str],
+    // Josev updates the binding subscribes to
+    pub messages: &'a [&'a str],
+}
+
+pub struct Broker<'a> {
+    pub host: &'a str,
+    pub port: u16,
+}
+
+// topics of Josev, below an optional prefix (one Josev per EVSE)
+fn topic(prefix: Option<&str>, topic: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{}/{}", prefix.trim_end_matches('/'), topic),
+        None => topic.to_string(),
+    }
+}
+
+fn list(out: &mut String, indent: usize, key: &str, names: &[&str]) {
+    let _ = writeln!(out, "{:indent$}{}:", "", key, indent = indent);
+    for name in names {
+        let _ = writeln!(out, "{:indent$}  - {}", "", name, indent = indent);
+    }
+}
+
+pub fn generate(mapping: &MqttMapping, broker: &Broker, prefix: Option<&str>) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# generated by {}/mqtt_config, do not edit", mapping.api);
+    let _ = write!(
+        out,
+        "\
+broker-host: {host}
+broker-port: {port}
+mapping-type: topic-pair
+publish-topic: {publish}
+",
+        host = broker.host,
+        port = broker.port,
+        publish = topic(prefix, PUBLISH_TOPIC),
+    );
+    let subscribe: Vec<String> = SUBSCRIBE_TOPICS
+        .iter()
+        .map(|subscribe| topic(prefix, subscribe))
+        .collect();
+    let subscribe: Vec<&str> = subscribe.iter().map(String::as_str).collect();
+    list(&mut out, 0, "subscribe-topic", &subscribe);
+
+    out.push_str(
+        "\
+to-mqtt:
+  timeout-ms: 500
+",
+    );
+    list(&mut out, 2, "verbs", mapping.to_josev);
+    let _ = write!(
+        out,
+        "  request-template:
+    id: ${{uuid()}}
+    name: ${{verb}}
+    type: request
+    data: ${{data}}
+  request-correlation-path: .id
+  response-extraction:
+    filter:
+      path: .type
+      value: response
+    data-path: .data
+    correlation-path: .id
+
+  event:
+    registrations:
+      # verbs to call to subscribe to events
+      - api: {api}
+        verb: {subscribe_verb}
+",
+        api = mapping.api,
+        subscribe_verb = mapping.subscribe_verb,
+    );
+    list(&mut out, 8, "events", mapping.events);
+    let _ = write!(
+        out,
+        "    template:
+      id: ${{uuid()}}
+      name: ${{event_name}}
+      type: update
+      data: ${{data}}
+
+from-mqtt:
+  # the AFB API to call on requests
+  api: {api}
+",
+        api = mapping.api,
+    );
+    list(&mut out, 2, "verbs", mapping.requests);
+    out.push_str(
+        "  request-extraction:
+    verb-path: .name
+    data-path: .data
+    filter:
+      path: .type
+      value: request
+
+  response-template:
+    id: ${request.id}
+    name: ${verb}
+    type: response
+    data: ${data}
+
+  # this will emit an event
+  event-extraction:
+    event-name-path: .name
+    data-path: .data
+    filter:
+      path: .type
+      value: update
+",
+    );
+    list(&mut out, 4, "events", mapping.messages);
+    out
+}
@@ -0,0 +1,101 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use std::collections::BTreeMap;
+
+use afbv4::prelude::*;
+
+pub type MessageHandler = fn(&AfbEventMsg, &AfbRqtData, &AfbCtxData) -> Result<(), AfbError>;
+pub type VerbHandler = fn(&AfbRequest, &AfbRqtData, &AfbCtxData) -> Result<(), AfbError>;
+
+//
+// What is done with a Josev message
+#[derive(Clone, Copy)]
+pub enum Consumer {
+    // handled by the binding
+    Handler(MessageHandler),
+    // re-emitted as an event of the josev api
+    Forward,
+}
+
+// consumer names usable from the configuration, besides built-in handlers
+const FORWARD: &str = "forward";
+const IGNORE: &str = "ignore";
+
+//
+// Josev messages the binding subscribes to, and their consumer
+pub struct MessageRegistry {
+    // built-in handlers, by the name of the message they were written for
+    handlers: &'static [(&'static str, MessageHandler)],
+    messages: Vec<(&'static str, Consumer)>,
+}
+
+impl MessageRegistry {
+    // every built-in handler consumes its own message
+    pub fn new(handlers: &'static [(&'static str, MessageHandler)]) -> Self {
+        MessageRegistry {
+            handlers,
+            messages: handlers
+                .iter()
+                .map(|(name, handler)| (*name, Consumer::Handler(*handler)))
+                .collect(),
+        }
+    }
+
+    fn handler(&self, name: &str) -> Option<MessageHandler> {
+        self.handlers
+            .iter()
+            .find(|(handler_name, _)| *handler_name == name)
+            .map(|(_, handler)| *handler)
+    }
+
+    //
+    // Apply the "josev_messages" configuration: an object mapping message names
+    // to a built-in handler name, "forward" or "ignore"
+    pub fn configure(&mut self, jconf: &JsoncObj) -> Result<(), AfbError> {
+        let entries: BTreeMap<String, String> = serde_json::from_str(&jconf.to_string())
+            .or_else(|error| afb_error!("josev-registry", "'josev_messages' malformed: {}", error))?;
+
+        for (message, consumer) in entries {
+            let consumer = match consumer.as_str() {
+                FORWARD => Some(Consumer::Forward),
+                IGNORE => None,
+                name => match self.handler(name) {
+                    Some(handler) => Some(Consumer::Handler(handler)),
+                    None => {
+                        return afb_error!(
+                            "josev-registry",
+                            "'josev_messages' {}: no such handler {}",
+                            message,
+                            name
+                        )
+                    }
+                },
+            };
+
+            self.messages.retain(|(name, _)| *name != message);
+            if let Some(consumer) = consumer {
+                self.messages.push((to_static_str(message), consumer));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn messages(&self) -> &[(&'static str, Consumer)] {
+        &self.messages
+    }
+
+    // names to subscribe to through from_mqtt
+    pub fn names(&self) -> Vec<&'static str> {
+        self.messages.iter().map(|(name, _)| *name).collect()
+    }
+}
@@ -0,0 +1,750 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::josev;
+use afbv4::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use typesv4::prelude::*;
+
+// number of transitions kept for the session_state verb
+const SESSION_HISTORY: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    // no cable
+    Idle,
+    // cable plugged, no (or basic) communication
+    Plugged,
+    // SLAC matching in progress
+    Slac,
+    // ISO 15118 / DIN session negotiation
+    HlcNegotiation,
+    Authorized,
+    Charging,
+    // EV back to state B, or ISO 15118-20 pause
+    Paused,
+    Stopping,
+    Fault,
+}
+
+impl SessionState {
+    // transitions allowed from this state, unplugging and faults are always allowed
+    fn allows(&self, to: SessionState) -> bool {
+        use SessionState::*;
+        if matches!(to, Idle | Fault) {
+            return true;
+        }
+        match self {
+            Idle => matches!(to, Plugged),
+            // basic charging may start without, or while, matching
+            Plugged => matches!(to, Slac | HlcNegotiation | Charging | Stopping),
+            Slac => matches!(to, Plugged | HlcNegotiation | Charging | Stopping),
+            // back to plugged when the negotiation fails (basic charging)
+            HlcNegotiation => matches!(to, Plugged | Authorized | Charging | Paused | Stopping),
+            Authorized => matches!(to, HlcNegotiation | Charging | Paused | Stopping),
+            Charging => matches!(to, Paused | Stopping),
+            // a paused EV may match again before resuming
+            Paused => matches!(to, Slac | HlcNegotiation | Authorized | Charging | Stopping),
+            Stopping => matches!(to, Plugged | Slac | HlcNegotiation),
+            // a fault outlives the plug events, until unplugged or re-armed
+            Fault => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct SessionTransition {
+    pub from: SessionState,
+    pub to: SessionState,
+    pub cause: String,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(schema_with = "josev::rfc3339_schema")]
+    pub timestamp: OffsetDateTime,
+    // time spent in the previous state (s)
+    pub duration: f64,
+}
+
+AfbDataConverter!(session_status, SessionStatus);
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct SessionStatus {
+    pub state: SessionState,
+    // time spent in the current state (s)
+    pub duration: f64,
+    pub transitions: Vec<SessionTransition>,
+}
+
+AfbDataConverter!(availability_status, AvailabilityStatus);
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct AvailabilityStatus {
+    pub evse_id: String,
+    pub operational_status: josev::OperationalStatus,
+    // inoperative requested, the ongoing session is allowed to finish first
+    pub scheduled: bool,
+    pub status_code: josev::CsStatusAndLimitsStatusCode,
+}
+
+//
+// Current (or last) transaction of an EVSE, as reported by Josev,
+// kept for the billing component
+AfbDataConverter!(transaction_record, TransactionRecord);
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct TransactionRecord {
+    pub evse_id: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub transaction_id: Option<String>,
+    pub status: josev::TransactionStatus,
+    // when the transaction was started (or first seen)
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(schema_with = "josev::rfc3339_schema")]
+    pub started: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    #[schemars(schema_with = "josev::rfc3339_schema")]
+    pub updated: OffsetDateTime,
+    #[serde(
+        with = "time::serde::rfc3339::option",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    #[schemars(schema_with = "josev::rfc3339_schema")]
+    pub ended: Option<OffsetDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub stop_reason: Option<String>,
+}
+
+impl TransactionRecord {
+    pub fn new(msg: &josev::TransactionStatusUpdate) -> Self {
+        let now = OffsetDateTime::now_utc();
+        let mut record = TransactionRecord {
+            evse_id: msg.evse_id.clone(),
+            transaction_id: None,
+            status: msg.status,
+            started: now,
+            updated: now,
+            ended: None,
+            stop_reason: None,
+        };
+        record.update(msg);
+        record
+    }
+
+    // true when this update belongs to another transaction
+    pub fn is_new(&self, msg: &josev::TransactionStatusUpdate) -> bool {
+        self.ended.is_some()
+            || msg.status == josev::TransactionStatus::Started
+            || (msg.transaction_id.is_some()
+                && self.transaction_id.is_some()
+                && msg.transaction_id != self.transaction_id)
+    }
+
+    pub fn update(&mut self, msg: &josev::TransactionStatusUpdate) {
+        let now = OffsetDateTime::now_utc();
+        self.status = msg.status;
+        self.updated = now;
+        if msg.transaction_id.is_some() {
+            self.transaction_id = msg.transaction_id.clone();
+        }
+        if msg.status == josev::TransactionStatus::Ended {
+            self.ended = Some(now);
+            self.stop_reason = msg.stop_reason.clone();
+        }
+    }
+
+    // the update forwarded to the charge manager
+    pub fn charging_msg(&self) -> ChargingMsg {
+        let id = self.transaction_id.clone();
+        match self.status {
+            josev::TransactionStatus::Started => ChargingMsg::TransactionStarted { id },
+            josev::TransactionStatus::Updated => ChargingMsg::TransactionUpdated { id },
+            josev::TransactionStatus::Ended => ChargingMsg::TransactionEnded {
+                id,
+                reason: self.stop_reason.clone(),
+            },
+        }
+    }
+}
+
+//
+// What survives a binder restart, saved each time it changes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SessionSnapshot {
+    pub state: SessionState,
+    pub cp_state: josev::ControlPilotState,
+    pub contactor_closed: bool,
+    pub iso_state: Option<IsoState>,
+    pub payment_option: Option<PaymentOption>,
+    pub authorized_token: Option<String>,
+}
+
+impl SessionSnapshot {
+    //
+    // Align a session saved before a restart with the relay state of a plugged EV:
+    // the saved state must not contradict it
+    pub fn reconcile(&mut self, closed: bool) {
+        self.contactor_closed = closed;
+        self.cp_state = if closed {
+            josev::ControlPilotState::C2
+        } else {
+            josev::ControlPilotState::B2
+        };
+        self.state = match (self.state, closed) {
+            // the EV stopped drawing power while the binder was down
+            (SessionState::Charging, false) => SessionState::Paused,
+            (SessionState::Stopping, false) | (SessionState::Idle, false) => SessionState::Plugged,
+            // power was granted while the binder was down
+            (
+                SessionState::Idle
+                | SessionState::Plugged
+                | SessionState::Slac
+                | SessionState::HlcNegotiation
+                | SessionState::Authorized
+                | SessionState::Paused,
+                true,
+            ) => SessionState::Charging,
+            (state, _) => state,
+        };
+    }
+}
+
+//
+// The charging session, its state machine and what was learned
+// about the EV and the station during the session
+pub struct Session {
+    state: SessionState,
+    entered: Instant,
+    history: VecDeque<SessionTransition>,
+
+    // where the session is saved, if anywhere
+    file: Option<&'static str>,
+
+    // current charging state
+    cp_state: josev::ControlPilotState,
+
+    // current contactor state
+    contactor_closed: bool,
+
+    // selected iso state
+    iso_state: Option<IsoState>,
+
+    // selected payment mode of the charging session (EIM, PnC)
+    payment_option: Option<PaymentOption>,
+
+    // token accepted for the current session, replayed when a paused session resumes
+    authorized_token: Option<String>,
+}
+
+impl Session {
+    pub fn new(file: Option<&'static str>) -> Self {
+        Session {
+            state: SessionState::Idle,
+            entered: Instant::now(),
+            history: VecDeque::with_capacity(SESSION_HISTORY),
+            file,
+            cp_state: josev::ControlPilotState::A1,
+            contactor_closed: false,
+            iso_state: None,
+            payment_option: None,
+            authorized_token: None,
+        }
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    pub fn cp_state(&self) -> josev::ControlPilotState {
+        self.cp_state
+    }
+
+    pub fn contactor_closed(&self) -> bool {
+        self.contactor_closed
+    }
+
+    pub fn iso_state(&self) -> Option<IsoState> {
+        self.iso_state
+    }
+
+    pub fn payment_option(&self) -> Option<PaymentOption> {
+        self.payment_option
+    }
+
+    pub fn authorized_token(&self) -> Option<&str> {
+        self.authorized_token.as_deref()
+    }
+
+    //
+    // Setters of what is saved, the session file is written when the value changes
+    pub fn set_cp_state(&mut self, cp_state: josev::ControlPilotState) -> Result<(), AfbError> {
+        if self.cp_state == cp_state {
+            return Ok(());
+        }
+        self.cp_state = cp_state;
+        self.save()
+    }
+
+    pub fn set_contactor_closed(&mut self, closed: bool) -> Result<(), AfbError> {
+        if self.contactor_closed == closed {
+            return Ok(());
+        }
+        self.contactor_closed = closed;
+        self.save()
+    }
+
+    pub fn set_iso_state(&mut self, iso_state: Option<IsoState>) -> Result<(), AfbError> {
+        if self.iso_state == iso_state {
+            return Ok(());
+        }
+        self.iso_state = iso_state;
+        self.save()
+    }
+
+    pub fn set_payment_option(
+        &mut self,
+        payment_option: Option<PaymentOption>,
+    ) -> Result<(), AfbError> {
+        if self.payment_option == payment_option {
+            return Ok(());
+        }
+        self.payment_option = payment_option;
+        self.save()
+    }
+
+    pub fn set_authorized_token(&mut self, token: Option<String>) -> Result<(), AfbError> {
+        if self.authorized_token == token {
+            return Ok(());
+        }
+        self.authorized_token = token;
+        self.save()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state == SessionState::Paused
+    }
+
+    // an EV session is going on, beyond a mere plugged cable
+    pub fn is_active(&self) -> bool {
+        !matches!(
+            self.state,
+            SessionState::Idle | SessionState::Plugged | SessionState::Fault
+        )
+    }
+
+    // an HLC session is going on, Josev keeps exchanging messages with the station
+    pub fn is_hlc(&self) -> bool {
+        match self.state {
+            SessionState::Slac | SessionState::HlcNegotiation | SessionState::Authorized => true,
+            SessionState::Charging => self.iso_state.is_some(),
+            _ => false,
+        }
+    }
+
+    //
+    // Move to a new state, returns false when already in this state
+    pub fn transition(&mut self, to: SessionState, cause: &'static str) -> Result<bool, AfbError> {
+        if self.state == to {
+            return Ok(false);
+        }
+        if !self.state.allows(to) {
+            return afb_error!(
+                "josev-session",
+                "invalid session transition {:?} -> {:?} ({})",
+                self.state,
+                to,
+                cause
+            );
+        }
+
+        self.record(to, cause);
+
+        if to == SessionState::Idle {
+            // nothing survives unplugging
+            self.iso_state = None;
+            self.payment_option = None;
+            self.authorized_token = None;
+        }
+
+        self.state = to;
+        self.entered = Instant::now();
+        Ok(true)
+    }
+
+    fn record(&mut self, to: SessionState, cause: &'static str) {
+        if self.history.len() == SESSION_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(SessionTransition {
+            from: self.state,
+            to,
+            cause: cause.to_string(),
+            timestamp: OffsetDateTime::now_utc(),
+            duration: self.entered.elapsed().as_secs_f64(),
+        });
+    }
+
+    pub fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot {
+            state: self.state,
+            cp_state: self.cp_state,
+            contactor_closed: self.contactor_closed,
+            iso_state: self.iso_state,
+            payment_option: self.payment_option,
+            authorized_token: self.authorized_token.clone(),
+        }
+    }
+
+    //
+    // Take back a saved session, whatever the current state
+    pub fn restore(&mut self, snapshot: SessionSnapshot, cause: &'static str) {
+        self.record(snapshot.state, cause);
+        self.state = snapshot.state;
+        self.entered = Instant::now();
+        self.cp_state = snapshot.cp_state;
+        self.contactor_closed = snapshot.contactor_closed;
+        self.iso_state = snapshot.iso_state;
+        self.payment_option = snapshot.payment_option;
+        self.authorized_token = snapshot.authorized_token;
+    }
+
+    //
+    // Write the session file, through a temporary file so that
+    // a crash never leaves a truncated one
+    pub fn save(&self) -> Result<(), AfbError> {
+        let Some(file) = self.file else {
+            return Ok(());
+        };
+        let content = serde_json::to_string(&self.snapshot())
+            .or_else(|error| afb_error!("josev-session", "cannot serialize session: {}", error))?;
+        let tmp = format!("{}.tmp", file);
+        std::fs::write(&tmp, content)
+            .and_then(|_| std::fs::rename(&tmp, file))
+            .or_else(|error| afb_error!("josev-session", "cannot save {}: {}", file, error))
+    }
+
+    // the saved session, None when there is none
+    pub fn load(&self) -> Result<Option<SessionSnapshot>, AfbError> {
+        let Some(file) = self.file else {
+            return Ok(None);
+        };
+        let content = match std::fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return afb_error!("josev-session", "cannot read {}: {}", file, error),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .or_else(|error| afb_error!("josev-session", "{} malformed: {}", file, error))
+    }
+
+    pub fn status(&self) -> SessionStatus {
+        SessionStatus {
+            state: self.state,
+            duration: self.entered.elapsed().as_secs_f64(),
+            transitions: self.history.iter().cloned().collect(),
+        }
+    }
+}
+
+//
+// CP state reported to Josev on a plug event of the charge manager, none when
+// the event does not tell it: a locked connector says nothing about the EV
+// requesting power, the CP stays as it is
+pub fn plug_cp_state(plugged: PlugState) -> Option<josev::ControlPilotState> {
+    match plugged {
+        PlugState::PlugIn => Some(josev::ControlPilotState::B2),
+        PlugState::Lock => None,
+        PlugState::Error | PlugState::PlugOut | PlugState::Unknown => {
+            Some(josev::ControlPilotState::A1)
+        }
+    }
+}
+
+pub fn session_registers() -> Result<(), AfbError> {
+    session_status::register()?;
+    transaction_record::register()?;
+    availability_status::register()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use SessionState::*;
+
+    fn walk(states: &[SessionState]) -> Session {
+        let mut session = Session::new(None);
+        for state in states {
+            assert!(session.transition(*state, "test").unwrap(), "{:?}", state);
+        }
+        session
+    }
+
+    #[test]
+    fn hlc_session() {
+        let session = walk(&[
+            Plugged,
+            Slac,
+            HlcNegotiation,
+            Authorized,
+            Charging,
+            Paused,
+            Slac,
+            HlcNegotiation,
+            Charging,
+            Stopping,
+            Plugged,
+            Idle,
+        ]);
+        assert_eq!(session.status().transitions.len(), 12);
+    }
+
+    #[test]
+    fn basic_charging() {
+        walk(&[Plugged, Charging, Paused, Charging, Stopping, Plugged]);
+        // the charge manager gives up on SLAC
+        walk(&[Plugged, Slac, Charging]);
+        // SLAC matched, the negotiation failed
+        walk(&[Plugged, Slac, HlcNegotiation, Plugged, Charging]);
+    }
+
+    #[test]
+    fn hlc() {
+        let mut session = walk(&[Plugged]);
+        assert!(!session.is_hlc());
+        session.transition(Slac, "test").unwrap();
+        assert!(session.is_hlc());
+        // basic charging
+        session.transition(Charging, "test").unwrap();
+        assert!(!session.is_hlc());
+
+        let mut session = walk(&[Plugged, Slac, HlcNegotiation]);
+        session.set_iso_state(Some(IsoState::Iso2)).unwrap();
+        session.transition(Charging, "test").unwrap();
+        assert!(session.is_hlc());
+        session.transition(Paused, "test").unwrap();
+        assert!(!session.is_hlc());
+    }
+
+    #[test]
+    fn same_state() {
+        let mut session = walk(&[Plugged]);
+        assert!(!session.transition(Plugged, "test").unwrap());
+        assert_eq!(session.status().transitions.len(), 1);
+    }
+
+    #[test]
+    fn invalid_transition() {
+        let mut session = Session::new(None);
+        assert!(session.transition(Charging, "test").is_err());
+        assert_eq!(session.state(), Idle);
+        assert!(session.status().transitions.is_empty());
+
+        let mut session = walk(&[Plugged, Slac, HlcNegotiation, Charging]);
+        assert!(session.transition(Authorized, "test").is_err());
+        assert!(session.transition(Plugged, "test").is_err());
+        assert_eq!(session.state(), Charging);
+    }
+
+    #[test]
+    fn fault() {
+        for state in [
+            Idle,
+            Plugged,
+            Slac,
+            HlcNegotiation,
+            Authorized,
+            Charging,
+            Paused,
+            Stopping,
+        ] {
+            assert!(state.allows(Fault), "{:?}", state);
+            assert!(state.allows(Idle), "{:?}", state);
+        }
+        let mut session = walk(&[Plugged, Fault]);
+        for state in [
+            Plugged,
+            Slac,
+            HlcNegotiation,
+            Authorized,
+            Charging,
+            Paused,
+            Stopping,
+        ] {
+            assert!(session.transition(state, "test").is_err(), "{:?}", state);
+        }
+        assert!(session.transition(Idle, "test").unwrap());
+    }
+
+    #[test]
+    fn saved_session() {
+        let file: &'static str = Box::leak(
+            std::env::temp_dir()
+                .join(format!("josev-session-{}.json", std::process::id()))
+                .to_string_lossy()
+                .into_owned()
+                .into_boxed_str(),
+        );
+        let _ = std::fs::remove_file(file);
+
+        let mut session = Session::new(Some(file));
+        assert!(session.load().unwrap().is_none());
+
+        // each change of a saved field is written at once
+        session.transition(Plugged, "test").unwrap();
+        session.save().unwrap();
+        session.set_cp_state(josev::ControlPilotState::C2).unwrap();
+        assert_eq!(
+            session.load().unwrap().unwrap().cp_state,
+            josev::ControlPilotState::C2
+        );
+        session.set_contactor_closed(true).unwrap();
+        assert!(session.load().unwrap().unwrap().contactor_closed);
+        session.set_iso_state(Some(IsoState::Iso20)).unwrap();
+        session
+            .set_payment_option(Some(PaymentOption::Pnc))
+            .unwrap();
+        session
+            .set_authorized_token(Some("token".to_string()))
+            .unwrap();
+
+        let mut restarted = Session::new(Some(file));
+        let snapshot = restarted.load().unwrap().unwrap();
+        restarted.restore(snapshot, "test");
+        assert_eq!(restarted.state(), Plugged);
+        assert_eq!(restarted.cp_state(), josev::ControlPilotState::C2);
+        assert!(restarted.contactor_closed());
+        assert!(matches!(restarted.iso_state(), Some(IsoState::Iso20)));
+        assert!(matches!(
+            restarted.payment_option(),
+            Some(PaymentOption::Pnc)
+        ));
+        assert_eq!(restarted.authorized_token(), Some("token"));
+
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn unplugging_ends_the_session() {
+        let mut session = walk(&[Plugged, Slac, HlcNegotiation]);
+        session.iso_state = Some(IsoState::Iso2);
+        session.payment_option = Some(PaymentOption::Eim);
+        session.authorized_token = Some("token".to_string());
+        session.transition(Idle, "test").unwrap();
+        assert!(session.iso_state.is_none());
+        assert!(session.payment_option.is_none());
+        assert!(session.authorized_token.is_none());
+    }
+
+    #[test]
+    fn locking_keeps_the_cp_state() {
+        assert_eq!(
+            plug_cp_state(PlugState::PlugIn),
+            Some(josev::ControlPilotState::B2)
+        );
+        // locked before the EV asks for power, or while it is charging
+        assert_eq!(plug_cp_state(PlugState::Lock), None);
+        assert_eq!(
+            plug_cp_state(PlugState::PlugOut),
+            Some(josev::ControlPilotState::A1)
+        );
+        assert_eq!(
+            plug_cp_state(PlugState::Error),
+            Some(josev::ControlPilotState::A1)
+        );
+    }
+
+    fn snapshot(state: SessionState, contactor_closed: bool) -> SessionSnapshot {
+        SessionSnapshot {
+            state,
+            cp_state: if contactor_closed {
+                josev::ControlPilotState::C2
+            } else {
+                josev::ControlPilotState::B2
+            },
+            contactor_closed,
+            iso_state: None,
+            payment_option: None,
+            authorized_token: None,
+        }
+    }
+
+    #[test]
+    fn recovered_state_follows_the_relay() {
+        // saved while charging, the relay opened while the binder was down
+        let mut saved = snapshot(Charging, true);
+        saved.reconcile(false);
+        assert_eq!(saved.state, Paused);
+        assert!(!saved.contactor_closed);
+        assert_eq!(saved.cp_state, josev::ControlPilotState::B2);
+
+        let mut saved = snapshot(Stopping, true);
+        saved.reconcile(false);
+        assert_eq!(saved.state, Plugged);
+
+        let mut saved = snapshot(Authorized, false);
+        saved.reconcile(true);
+        assert_eq!(saved.state, Charging);
+        assert_eq!(saved.cp_state, josev::ControlPilotState::C2);
+
+        // a fault lasts until the cable is unplugged
+        let mut saved = snapshot(Fault, false);
+        saved.reconcile(true);
+        assert_eq!(saved.state, Fault);
+
+        let mut saved = snapshot(Slac, false);
+        saved.reconcile(false);
+        assert_eq!(saved.state, Slac);
+    }
+
+    #[test]
+    fn transaction_updates_are_forwarded() {
+        let update = |status, transaction_id: Option<&str>, stop_reason: Option<&str>| {
+            josev::TransactionStatusUpdate {
+                evse_id: "DE*PNX*E12345*1".to_string(),
+                status,
+                transaction_id: transaction_id.map(str::to_string),
+                stop_reason: stop_reason.map(str::to_string),
+            }
+        };
+        let mut record =
+            TransactionRecord::new(&update(josev::TransactionStatus::Started, Some("T1"), None));
+        assert!(matches!(
+            record.charging_msg(),
+            ChargingMsg::TransactionStarted { id: Some(ref id) } if id == "T1"
+        ));
+
+        // the ID is kept when an update does not repeat it
+        let updated = update(josev::TransactionStatus::Updated, None, None);
+        assert!(!record.is_new(&updated));
+        record.update(&updated);
+        assert!(matches!(
+            record.charging_msg(),
+            ChargingMsg::TransactionUpdated { id: Some(ref id) } if id == "T1"
+        ));
+
+        record.update(&update(
+            josev::TransactionStatus::Ended,
+            None,
+            Some("EVDisconnected"),
+        ));
+        assert!(matches!(
+            record.charging_msg(),
+            ChargingMsg::TransactionEnded { id: Some(ref id), reason: Some(ref reason) }
+                if id == "T1" && reason == "EVDisconnected"
+        ));
+        assert!(record.is_new(&updated));
+    }
+}
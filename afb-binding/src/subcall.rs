@@ -0,0 +1,311 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use afbv4::prelude::*;
+use serde::Deserialize;
+
+//
+// Continuation of an asynchronous subcall, called from the event loop once
+// the reply is received (or all attempts failed). It must not expect any lock to be held.
+pub type SubcallDone =
+    Box<dyn FnOnce(AfbApiV4, Result<&AfbRqtData, AfbError>) -> Result<(), AfbError>>;
+
+//
+// Timeout and retries of the subcalls toward one api
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct SubcallPolicy {
+    // time to wait for a reply (ms), 0 waits forever
+    #[serde(default = "SubcallPolicy::default_timeout")]
+    pub timeout: u32,
+    // attempts after the first one, on error or timeout,
+    // only for verbs that can safely be called twice
+    #[serde(default = "SubcallPolicy::default_retries")]
+    pub retries: u32,
+    // delay before the first retry (ms), doubled on each retry
+    #[serde(default = "SubcallPolicy::default_backoff")]
+    pub backoff: u32,
+}
+
+impl SubcallPolicy {
+    fn default_timeout() -> u32 {
+        3000
+    }
+
+    fn default_retries() -> u32 {
+        0
+    }
+
+    fn default_backoff() -> u32 {
+        200
+    }
+
+    // default policy of idempotent reads
+    pub fn reads() -> Self {
+        SubcallPolicy {
+            retries: 2,
+            ..Self::default()
+        }
+    }
+
+    // the same policy without retries, for verbs that must not be called twice
+    pub fn once(self) -> Self {
+        SubcallPolicy { retries: 0, ..self }
+    }
+
+    fn backoff(&self, attempt: u32) -> u32 {
+        self.backoff.saturating_mul(1 << (attempt - 1).min(16))
+    }
+}
+
+impl Default for SubcallPolicy {
+    fn default() -> Self {
+        SubcallPolicy {
+            timeout: Self::default_timeout(),
+            retries: Self::default_retries(),
+            backoff: Self::default_backoff(),
+        }
+    }
+}
+
+// only used from the event loop thread
+type Pending = Rc<RefCell<PendingCall>>;
+type Issue = Rc<dyn Fn(AfbApiV4, AttemptCtx) -> Result<(), AfbError>>;
+
+struct PendingCall {
+    apiname: &'static str,
+    verb: &'static str,
+    policy: SubcallPolicy,
+    attempt: u32,
+    // sends the request of the current attempt
+    issue: Issue,
+    // set once the call is over, late replies are then dropped
+    over: bool,
+    done: Option<SubcallDone>,
+}
+
+struct AttemptCtx {
+    pending: Pending,
+    attempt: u32,
+}
+
+impl AttemptCtx {
+    // false when the attempt was answered, timed out or superseded
+    fn is_current(&self) -> bool {
+        let call = self.pending.borrow();
+        call.attempt == self.attempt && !call.over
+    }
+}
+
+// the call is over, the continuation gets the final outcome
+fn complete(
+    api: AfbApiV4,
+    pending: &Pending,
+    reply: Result<&AfbRqtData, AfbError>,
+) -> Result<(), AfbError> {
+    let done = {
+        let mut call = pending.borrow_mut();
+        call.over = true;
+        call.done.take()
+    };
+    match done {
+        Some(done) => done(api, reply),
+        None => {
+            // nobody waits for this reply, at least say it failed
+            if let Err(error) = reply {
+                afb_log_msg!(Warning, api, "{}", error);
+            }
+            Ok(())
+        }
+    }
+}
+
+// schedule a new attempt, returns false when no retry is left
+fn retry(api: AfbApiV4, pending: &Pending, reason: &str) -> Result<bool, AfbError> {
+    let delay = {
+        let mut call = pending.borrow_mut();
+        if call.attempt >= call.policy.retries {
+            return Ok(false);
+        }
+        call.attempt += 1;
+        afb_log_msg!(
+            Notice,
+            api,
+            "{}/{} {}, retry {}/{}",
+            call.apiname,
+            call.verb,
+            reason,
+            call.attempt,
+            call.policy.retries
+        );
+        call.policy.backoff(call.attempt)
+    };
+
+    if delay == 0 {
+        attempt(api, pending)?;
+    } else {
+        AfbTimer::new("josev-subcall-retry")
+            .set_period(delay)
+            .set_decount(1)
+            .set_callback(retry_timer_cb)
+            .set_context(pending.clone())
+            .start()?;
+    }
+    Ok(true)
+}
+
+fn retry_timer_cb(timer: &AfbTimer, _decount: u32, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let pending = ctx.get_ref::<Pending>()?;
+    attempt(timer.get_apiv4(), pending)
+}
+
+// an attempt that cannot even be sent ends the call, the continuation gets the error
+fn attempt(api: AfbApiV4, pending: &Pending) -> Result<(), AfbError> {
+    match issue(api, pending) {
+        Ok(()) => Ok(()),
+        Err(error) => complete(api, pending, Err(error)),
+    }
+}
+
+fn timeout_timer_cb(timer: &AfbTimer, _decount: u32, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<AttemptCtx>()?;
+    if !ctx.is_current() {
+        return Ok(());
+    }
+
+    let api = timer.get_apiv4();
+    if retry(api, &ctx.pending, "timed out")? {
+        return Ok(());
+    }
+
+    let error = {
+        let call = ctx.pending.borrow();
+        afb_error!(
+            "josev-subcall",
+            "{}/{} timed out after {} attempt(s)",
+            call.apiname,
+            call.verb,
+            call.attempt + 1
+        )
+    };
+    complete(api, &ctx.pending, error)
+}
+
+fn subcall_cb(api: &AfbApi, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = ctx.get_ref::<AttemptCtx>()?;
+    if !ctx.is_current() {
+        // reply of an attempt given up on
+        return Ok(());
+    }
+
+    let api = api.get_apiv4();
+    if args.get_status() < 0 {
+        if retry(api, &ctx.pending, "failed")? {
+            return Ok(());
+        }
+        let error = {
+            let call = ctx.pending.borrow();
+            afb_error!(
+                "josev-subcall",
+                "{}/{} failed with status {}",
+                call.apiname,
+                call.verb,
+                args.get_status()
+            )
+        };
+        return complete(api, &ctx.pending, error);
+    }
+
+    complete(api, &ctx.pending, Ok(args))
+}
+
+fn issue(api: AfbApiV4, pending: &Pending) -> Result<(), AfbError> {
+    // never hold the borrow while calling, the reply may come back at once
+    let (attempt, timeout, issue) = {
+        let call = pending.borrow();
+        (call.attempt, call.policy.timeout, call.issue.clone())
+    };
+
+    if timeout > 0 {
+        AfbTimer::new("josev-subcall-timeout")
+            .set_period(timeout)
+            .set_decount(1)
+            .set_callback(timeout_timer_cb)
+            .set_context(AttemptCtx {
+                pending: pending.clone(),
+                attempt,
+            })
+            .start()?;
+    }
+
+    issue(
+        api,
+        AttemptCtx {
+            pending: pending.clone(),
+            attempt,
+        },
+    )
+}
+
+//
+// Call a verb without blocking the event loop, `done` is called with the reply.
+// `args` builds the arguments of each attempt. Every failure, including a request
+// that cannot be sent, only reaches `done`: the error returned is the one of `done`.
+pub fn call_with<T: 'static, F>(
+    api: AfbApiV4,
+    apiname: &'static str,
+    verb: &'static str,
+    policy: SubcallPolicy,
+    args: F,
+    done: Option<SubcallDone>,
+) -> Result<(), AfbError>
+where
+    F: Fn() -> Result<T, AfbError> + 'static,
+{
+    let pending = Rc::new(RefCell::new(PendingCall {
+        apiname,
+        verb,
+        policy,
+        attempt: 0,
+        issue: Rc::new(move |api, ctx| {
+            AfbSubCall::call_async(api, apiname, verb, args()?, subcall_cb, ctx)
+        }),
+        over: false,
+        done,
+    }));
+    attempt(api, &pending)
+}
+
+pub fn call_async<T: Clone + 'static>(
+    api: AfbApiV4,
+    apiname: &'static str,
+    verb: &'static str,
+    args: T,
+    policy: SubcallPolicy,
+    done: Option<SubcallDone>,
+) -> Result<(), AfbError> {
+    call_with(api, apiname, verb, policy, move || Ok(args.clone()), done)
+}
+
+//
+// Fire and forget: failures are only logged
+pub fn send<T: Clone + 'static>(
+    api: AfbApiV4,
+    apiname: &'static str,
+    verb: &'static str,
+    args: T,
+    policy: SubcallPolicy,
+) -> Result<(), AfbError> {
+    call_async(api, apiname, verb, args, policy, None)
+}
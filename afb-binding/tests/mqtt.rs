@@ -0,0 +1,187 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+//
+// Native MQTT client. Tests marked ignored need a local broker:
+//   mosquitto -p 1883 &
+//   cargo test -p josev-binding --features native-mqtt -- --include-ignored
+// MQTT_HOST and MQTT_PORT select another broker.
+//
+#![cfg(feature = "native-mqtt")]
+
+#[path = "../src/mqtt.rs"]
+#[allow(dead_code)]
+mod mqtt;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mqtt::*;
+use serde_json::json;
+
+fn config(client_id: &str, publish_topic: &str, subscribe_topic: &str) -> MqttConfig {
+    let mut config: MqttConfig = serde_json::from_value(json!({
+        "client_id": client_id,
+        "publish_topic": publish_topic,
+        "subscribe_topics": [subscribe_topic],
+    }))
+    .unwrap();
+    if let Ok(host) = std::env::var("MQTT_HOST") {
+        config.host = host;
+    }
+    if let Ok(port) = std::env::var("MQTT_PORT") {
+        config.port = port.parse().unwrap();
+    }
+    config
+}
+
+// the binding side and a fake Josev, on the Josev topic pair
+fn link_pair(test: &str) -> (MqttLink, MqttLink) {
+    let binding =
+        MqttLink::connect(&config(&format!("{}-cs", test), "cs/josev", "josev/cs")).unwrap();
+    let josev =
+        MqttLink::connect(&config(&format!("{}-josev", test), "josev/cs", "cs/josev")).unwrap();
+    // let both subscriptions reach the broker
+    thread::sleep(Duration::from_millis(500));
+    (binding, josev)
+}
+
+fn receive(link: &MqttLink) -> Envelope {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        match link.try_recv() {
+            Some(received) => return received.unwrap(),
+            None => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    panic!("no message received");
+}
+
+#[test]
+fn envelope_format() {
+    let request = Envelope::request("cs_parameters", json!({}));
+    let message = serde_json::to_value(&request).unwrap();
+    assert_eq!(message["name"], "cs_parameters");
+    assert_eq!(message["type"], "request");
+    assert_eq!(message["data"], json!({}));
+    assert!(!request.id.is_empty());
+
+    let response = Envelope::response(&request, json!({"status": "accepted"}));
+    assert_eq!(response.id, request.id);
+    assert_eq!(response.name, request.name);
+    assert_eq!(serde_json::to_value(&response).unwrap()["type"], "response");
+}
+
+#[test]
+fn envelope_from_josev() {
+    let update: Envelope = serde_json::from_str(
+        r#"{"name": "slac_status", "type": "update", "data": {"evse_id": "DE*PNX*E12345*1", "status": "matched"}}"#,
+    )
+    .unwrap();
+    assert_eq!(update.kind, MessageType::Update);
+    assert_eq!(update.data["status"], "matched");
+    // updates may come without id
+    assert_eq!(update.id, "");
+
+    assert!(serde_json::from_str::<Envelope>(r#"{"name": "x", "type": "notification"}"#).is_err());
+}
+
+#[test]
+fn josev_requests_are_answered_by_known_verbs() {
+    let verbs = ["cs_parameters", "meter_values"];
+    let request: Envelope = serde_json::from_str(
+        r#"{"id": "1", "name": "meter_values", "type": "request", "data": {"evse_id": "DE*PNX*E12345*1"}}"#,
+    )
+    .unwrap();
+    assert_eq!(request.verb(&verbs), Some("meter_values"));
+
+    // answered at once, rather than left to time out on the Josev side
+    let unknown: Envelope =
+        serde_json::from_str(r#"{"id": "2", "name": "reboot", "type": "request", "data": {}}"#)
+            .unwrap();
+    assert_eq!(unknown.verb(&verbs), None);
+    assert_eq!(
+        serde_json::to_value(Envelope::error(&unknown, "unknown request reboot")).unwrap(),
+        json!({
+            "id": "2",
+            "name": "reboot",
+            "type": "response",
+            "data": {"error": "unknown request reboot"}
+        })
+    );
+}
+
+#[test]
+fn config_defaults() {
+    let config: MqttConfig = serde_json::from_str("{}").unwrap();
+    assert_eq!(config.host, "localhost");
+    assert_eq!(config.port, 1883);
+    assert_eq!(config.publish_topic, "cs/josev");
+    assert!(config.subscribe_topics.contains(&"josev/cs".to_string()));
+}
+
+#[test]
+#[ignore = "needs a local MQTT broker"]
+fn josev_request_is_answered() {
+    let (binding, josev) = link_pair("request");
+
+    let request = Envelope::request("cs_parameters", json!({}));
+    josev.publish(&request).unwrap();
+
+    let received = receive(&binding);
+    assert_eq!(received, request);
+
+    binding
+        .publish(&Envelope::response(&received, json!({"parameters": []})))
+        .unwrap();
+    let response = receive(&josev);
+    assert_eq!(response.kind, MessageType::Response);
+    assert_eq!(response.id, request.id);
+    assert_eq!(response.data, json!({"parameters": []}));
+}
+
+#[test]
+#[ignore = "needs a local MQTT broker"]
+fn updates_both_ways() {
+    let (binding, josev) = link_pair("update");
+
+    let cp_status = Envelope::update("cp_status", json!({"evse_id": "1", "state": "B2"}));
+    binding.publish(&cp_status).unwrap();
+    assert_eq!(receive(&josev), cp_status);
+
+    let hlc_charging = Envelope::update("hlc_charging", json!({"evse_id": "1", "status": true}));
+    josev.publish(&hlc_charging).unwrap();
+    assert_eq!(receive(&binding), hlc_charging);
+
+    // nothing echoed back on our own topic
+    thread::sleep(Duration::from_millis(200));
+    assert!(binding.try_recv().is_none());
+}
+
+#[test]
+#[ignore = "needs a local MQTT broker"]
+fn incoming_messages_wake_up_the_owner() {
+    let (binding, josev) = link_pair("wakeup");
+    assert!(!binding.clear_wakeup());
+
+    let slac_status =
+        Envelope::update("slac_status", json!({"evse_id": "1", "status": "matching"}));
+    josev.publish(&slac_status).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !binding.clear_wakeup() {
+        assert!(Instant::now() < deadline, "not woken up");
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(binding.try_recv().unwrap().unwrap(), slac_status);
+    assert!(!binding.clear_wakeup());
+}
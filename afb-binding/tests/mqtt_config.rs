@@ -0,0 +1,59 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+//
+// The committed etc/mqtt-config.yml must be what the mqtt_config verb
+// generates for the default configuration.
+//
+
+#[path = "../src/messages.rs"]
+mod messages;
+
+#[path = "../src/mqtt_config.rs"]
+mod mqtt_config;
+
+use messages::*;
+use mqtt_config::*;
+
+fn default_mapping() -> MqttMapping<'static> {
+    MqttMapping {
+        api: "josev",
+        subscribe_verb: "subscribe",
+        requests: &JOSEV_REQUESTS,
+        to_josev: &TO_JOSEV,
+        events: &JOSEV_EVENTS,
+        messages: &JOSEV_MESSAGES,
+    }
+}
+
+const BROKER: Broker = Broker {
+    host: "localhost",
+    port: 1883,
+};
+
+#[test]
+fn committed_config_is_generated() {
+    assert_eq!(
+        generate(&default_mapping(), &BROKER, None),
+        include_str!("../etc/mqtt-config.yml"),
+        "etc/mqtt-config.yml is out of date, regenerate it with the mqtt_config verb"
+    );
+}
+
+#[test]
+fn topics_follow_the_prefix() {
+    let config = generate(&default_mapping(), &BROKER, Some("evse1/"));
+    assert!(config.contains("publish-topic: evse1/cs/josev\n"));
+    for subscribe in SUBSCRIBE_TOPICS {
+        assert!(config.contains(&format!("  - evse1/{}\n", subscribe)));
+    }
+}
@@ -0,0 +1,520 @@
+/*
+ * Copyright (C) 2015-2024 IoT.bzh Company
+ * Author: Hugo Mercier <hugo.mercier@iot.bzh>
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ */
+
+//
+// Josev message types against the JSON Schemas the info verb publishes,
+// generated from these types. Each sample is parsed into its type and
+// serialized back: the output must validate and be the sample again,
+// optional fields omitted rather than null.
+//
+
+#[path = "../src/josev_types.rs"]
+#[allow(dead_code)]
+mod josev;
+
+#[path = "../src/args.rs"]
+#[allow(dead_code)]
+mod args;
+
+use jsonschema::{Draft, JSONSchema};
+use schemars::{schema_for, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+fn validator<T: JsonSchema>() -> JSONSchema {
+    JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .should_validate_formats(true)
+        .compile(&serde_json::to_value(schema_for!(T)).unwrap())
+        .unwrap()
+}
+
+fn check(message: &str, validator: &JSONSchema, data: &Value) {
+    if let Err(errors) = validator.validate(data) {
+        let errors: Vec<String> = errors.map(|error| error.to_string()).collect();
+        panic!("{} {}: {}", message, data, errors.join(", "));
+    }
+}
+
+fn assert_valid<T: JsonSchema>(message: &str, data: &Value) {
+    check(message, &validator::<T>(), data);
+}
+
+fn assert_invalid<T: JsonSchema>(message: &str, data: &Value) {
+    assert!(!validator::<T>().is_valid(data), "{} {} accepted", message, data);
+}
+
+// through the wire format, so that f32 values print as they are sent
+fn round_trip<T: DeserializeOwned + Serialize + JsonSchema>(message: &str, sample: Value) {
+    let validator = validator::<T>();
+    check(message, &validator, &sample);
+    let parsed: T = serde_json::from_value(sample.clone())
+        .unwrap_or_else(|error| panic!("{} {}: {}", message, sample, error));
+    let output: Value = serde_json::from_str(&serde_json::to_string(&parsed).unwrap()).unwrap();
+    check(message, &validator, &output);
+    assert_eq!(output, sample, "{}", message);
+}
+
+#[test]
+fn authorization() {
+    round_trip::<josev::AuthorizationUpdate>(
+        "authorization_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "id_token": "04A2B3C4", "token_type": "ISO14443", "status": "accepted"}),
+    );
+    round_trip::<josev::AuthorizationUpdate>(
+        "authorization_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "token_type": "no_authorization", "status": "deauthorized"}),
+    );
+    round_trip::<josev::AuthorizationRequest>(
+        "authorization_request",
+        json!({"evse_id": "DE*PNX*E12345*1", "id_token": "DEPNXC12345678", "token_type": "e_maid"}),
+    );
+    round_trip::<josev::AuthorizationRequest>("authorization_request", json!({"token_type": "key_code"}));
+    round_trip::<josev::AuthorizationResponse>("authorization_response", json!({"status": "rejected"}));
+}
+
+#[test]
+fn cable_check() {
+    round_trip::<josev::CableCheckRequest>(
+        "cable_check_request",
+        json!({"evse_id": "DE*PNX*E12345*1", "cable_check_action": "start"}),
+    );
+    round_trip::<josev::CableCheckResponse>(
+        "cable_check_response",
+        json!({"evse_id": "DE*PNX*E12345*1", "cable_check_status": "finished", "isolation_level": "no_imd"}),
+    );
+    round_trip::<josev::CableCheckResponse>(
+        "cable_check_response",
+        json!({"evse_id": "DE*PNX*E12345*1", "cable_check_status": "ongoing"}),
+    );
+}
+
+#[test]
+fn cp_status() {
+    round_trip::<josev::CpStatusUpdate>(
+        "cp_status_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "connector_id": 1, "state": "C2", "max_voltage": 6.0, "min_voltage": -12.0, "duty_cycle": 26.5}),
+    );
+    // no CP measure yet
+    round_trip::<josev::CpStatusUpdate>(
+        "cp_status_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "connector_id": 1, "state": "A1"}),
+    );
+    assert_invalid::<josev::CpStatusUpdate>(
+        "cp_status_update",
+        &json!({"evse_id": "DE*PNX*E12345*1", "connector_id": 1, "state": "B2", "duty_cycle": 100.5}),
+    );
+}
+
+#[test]
+fn cs_contactor_status() {
+    round_trip::<josev::CsContactorStatusRequest>("cs_contactor_status_request", json!({"evse_id": "DE*PNX*E12345*1"}));
+    round_trip::<josev::CsContactorStatusResponse>(
+        "cs_contactor_status_response",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "error", "info": "welded"}),
+    );
+    round_trip::<josev::CsContactorStatusResponse>(
+        "cs_contactor_status_response",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "closed"}),
+    );
+    round_trip::<josev::CsContactorStatusUpdate>(
+        "cs_contactor_status_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "opened"}),
+    );
+}
+
+#[test]
+fn power_electronics_setpoint() {
+    round_trip::<josev::PowerElectronicsSetpointRequest>(
+        "power_electronics_setpoint_request",
+        json!({
+            "evse_id": "DE*PNX*E12345*1",
+            "ev_limits": {"maximum_voltage": 500.0, "minimum_voltage": 150.0},
+            "is_precharge": true,
+            "dc": {"voltage": 400.0, "charge_current": 2.0},
+        }),
+    );
+    round_trip::<josev::PowerElectronicsSetpointRequest>(
+        "power_electronics_setpoint_request",
+        json!({
+            "evse_id": "DE*PNX*E12345*1",
+            "ev_limits": {"maximum_voltage": 400.0},
+            "ac": {"charge_active_power": 11000.0, "discharge_current": 16.0},
+        }),
+    );
+    round_trip::<josev::PowerElectronicsSetpointResponse>(
+        "power_electronics_setpoint_response",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "accepted"}),
+    );
+}
+
+#[test]
+fn cs_status_and_limits() {
+    round_trip::<josev::CsStatusAndLimitsResponse>(
+        "cs_status_and_limits_response",
+        json!({"evses": [
+            {
+                "evse_id": "DE*PNX*E12345*1",
+                "status_code": "evse_ready",
+                "ac": {"max_current": {"l1": 32.0, "l2": 32.0, "l3": 32.0}, "nominal_voltage": 230.0, "rcd_error": false},
+                "ac_bpt": {
+                    "evse_max_discharge_power": {"l1": 3680.0, "l2": 3680.0, "l3": 3680.0},
+                    "evse_min_discharge_power": {"l1": 0.0, "l2": 0.0, "l3": 0.0},
+                },
+            },
+            {
+                "evse_id": "DE*PNX*E12345*2",
+                "status_code": "evse_isolation_monitoring_active",
+                "dc": {
+                    "present_voltage": 400.0, "present_current": 50.5, "max_current": 125.0, "min_current": 0.0,
+                    "max_voltage": 500.0, "min_voltage": 150.0, "max_power": 50000.0, "peak_current_ripple": 2.0,
+                    "isolation_status": "no_imd",
+                },
+                "dc_bpt": {
+                    "evse_max_discharge_power": 25000.0, "evse_min_discharge_power": 0.0,
+                    "evse_max_discharge_current": 60.0, "evse_min_discharge_current": 0.0,
+                },
+            },
+            {"evse_id": "DE*PNX*E12345*3", "status_code": "evse_emergency_shutdown"},
+        ]}),
+    );
+}
+
+#[test]
+fn cs_parameters() {
+    round_trip::<josev::CsParametersResponse>(
+        "cs_parameters_response",
+        json!({
+            "sw_version": "1.2.0",
+            "hw_version": "A",
+            "number_of_evses": 1,
+            "parameters": [{
+                "evse_id": "DE*PNX*E12345*1",
+                "supports_eim": true,
+                "network_interface": "eth1",
+                "connectors": [
+                    {"id": 1, "services": {
+                        "ac": {"connector_type": "AC_three_phase_core", "control_mode": "dynamic", "nominal_voltage": 230},
+                        "ac_bpt": {
+                            "connector_type": "AC_single_phase_core", "nominal_voltage": 230,
+                            "bpt_channel": "unified", "generator_mode": "grid_following",
+                            "grid_island_detection_mode": "passive",
+                        },
+                    }},
+                    {"id": 2, "services": {
+                        "dc": {"connector_type": "DC_extended"},
+                        "dc_bpt": {"connector_type": "DC_combo_core", "control_mode": "scheduled", "nominal_voltage": 400},
+                    }},
+                ],
+            }],
+        }),
+    );
+}
+
+#[test]
+fn meter_values() {
+    round_trip::<josev::MeterValuesRequest>(
+        "meter_values_request",
+        json!({"evse_id": "DE*PNX*E12345*1", "timestamp": "2024-05-14T10:00:00Z"}),
+    );
+    round_trip::<josev::MeterValuesRequest>("meter_values_request", json!({"timestamp": "2024-05-14T10:00:00.5Z"}));
+    round_trip::<josev::MeterValuesResponse>(
+        "meter_values_response",
+        json!({
+            "evse_id": "DE*PNX*E12345*1",
+            "timestamp": "2024-05-14T10:00:00Z",
+            "voltage": {"l1": 230.5, "l2": 229.0, "l3": 231.25},
+            "current": {"l1": 16.0, "l2": 15.5, "l3": 16.25},
+            "power_factor": 0.75,
+            "frequency": 50.0,
+            "total_active_energy_imported": 12000.0,
+            "total_active_energy_exported": 0.0,
+            "total_reactive_energy_imported": 150.0,
+            "soc": 42.0,
+            "signed_meter_values": [{
+                "measurand": "Energy.Active.Import.Register",
+                "signed_meter_data": "AAEC",
+                "signing_method": "ECDSA-secp256r1-SHA256",
+                "encoding_method": "OCMF",
+                "public_key": "MFkw",
+            }],
+        }),
+    );
+    round_trip::<josev::MeterValuesResponse>(
+        "meter_values_response",
+        json!({
+            "timestamp": "2024-05-14T10:00:00Z",
+            "voltage": {"l1": 400.0, "l2": 0.0, "l3": 0.0},
+            "current": {"l1": 0.0, "l2": 0.0, "l3": 0.0},
+            "power_factor": 1.0,
+            "dc_current": 100.0,
+            "dc_voltage": 400.0,
+            "frequency": 0.0,
+            "total_active_energy_imported": 0.0,
+            "total_reactive_energy_imported": 0.0,
+        }),
+    );
+}
+
+#[test]
+fn meter_values_constraints() {
+    let response = |power_factor: f64, timestamp: &str| {
+        json!({
+            "timestamp": timestamp,
+            "voltage": {"l1": 230.0, "l2": 230.0, "l3": 230.0},
+            "current": {"l1": 0.0, "l2": 0.0, "l3": 0.0},
+            "power_factor": power_factor,
+            "frequency": 50.0,
+            "total_active_energy_imported": 0.0,
+            "total_reactive_energy_imported": 0.0,
+        })
+    };
+    assert_valid::<josev::MeterValuesResponse>("meter_values_response", &response(0.0, "2024-05-14T10:00:00+02:00"));
+    assert_invalid::<josev::MeterValuesResponse>("meter_values_response", &response(1.5, "2024-05-14T10:00:00Z"));
+    assert_invalid::<josev::MeterValuesResponse>("meter_values_response", &response(-0.5, "2024-05-14T10:00:00Z"));
+    assert_invalid::<josev::MeterValuesResponse>("meter_values_response", &response(1.0, "14/05/2024 10:00"));
+}
+
+#[test]
+fn stop_charging() {
+    round_trip::<josev::StopChargingRequest>("stop_charging_request", json!({"evse_id": "DE*PNX*E12345*1"}));
+    round_trip::<josev::StopChargingResponse>(
+        "stop_charging_response",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "accepted"}),
+    );
+}
+
+#[test]
+fn connector_lock() {
+    round_trip::<josev::ConnectorLockRequest>(
+        "connector_lock_request",
+        json!({"evse_id": "DE*PNX*E12345*1", "connector_id": 1}),
+    );
+    round_trip::<josev::ConnectorLockRequest>("connector_lock_request", json!({"evse_id": "DE*PNX*E12345*1"}));
+    round_trip::<josev::ConnectorLockResponse>(
+        "connector_lock_response",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "rejected", "info": "contactor closed"}),
+    );
+}
+
+#[test]
+fn josev_updates() {
+    round_trip::<josev::SlacStatusUpdate>(
+        "slac_status_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "run_id": "b4f2a1c0", "status": "basic_charging"}),
+    );
+    round_trip::<josev::HlcChargingUpdate>(
+        "hlc_charging_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": true}),
+    );
+    round_trip::<josev::TransactionStatusUpdate>(
+        "transaction_status_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "ended", "transaction_id": "42", "stop_reason": "EVDisconnected"}),
+    );
+    round_trip::<josev::TransactionStatusUpdate>(
+        "transaction_status_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "started"}),
+    );
+    // negative when discharging
+    round_trip::<josev::ChargeLimitUpdate>(
+        "iso15118_charge_limit_update",
+        json!({"evse_id": "DE*PNX*E12345*1", "limit": -7400.0}),
+    );
+    round_trip::<josev::ServiceStatusUpdate>(
+        "service_status_update",
+        json!({"service": "smart_charging", "software_version": "0.24.0", "api_version": "1.0.0", "status": "ready"}),
+    );
+}
+
+#[test]
+fn change_availability() {
+    round_trip::<josev::ChangeAvailabilityRequest>(
+        "change_availability_request",
+        json!({"evse_id": "DE*PNX*E12345*1", "operational_status": "inoperative"}),
+    );
+    round_trip::<josev::ChangeAvailabilityResponse>(
+        "change_availability_response",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "scheduled"}),
+    );
+}
+
+#[test]
+fn cp_pwm() {
+    round_trip::<josev::CpPwmRequest>(
+        "cp_pwm_request",
+        json!({"evse_id": "DE*PNX*E12345*1", "hlc": false, "current": 16.5, "error_state": false, "fault_state": false}),
+    );
+    round_trip::<josev::CpPwmRequest>(
+        "cp_pwm_request",
+        json!({"evse_id": "DE*PNX*E12345*1", "hlc": true, "error_state": false, "fault_state": true}),
+    );
+    round_trip::<josev::CpPwmResponse>(
+        "cp_pwm_response",
+        json!({"evse_id": "DE*PNX*E12345*1", "status": "invalid", "info": "current above the cable rating"}),
+    );
+
+    // the current is read with one decimal
+    let request: josev::CpPwmRequest = serde_json::from_value(
+        json!({"evse_id": "DE*PNX*E12345*1", "hlc": false, "current": 9.96, "error_state": false, "fault_state": false}),
+    )
+    .unwrap();
+    assert_eq!(request.current, Some(10.0));
+    assert_invalid::<josev::CpPwmRequest>(
+        "cp_pwm_request",
+        &json!({"evse_id": "DE*PNX*E12345*1", "hlc": false, "current": -6.0, "error_state": false, "fault_state": false}),
+    );
+}
+
+#[test]
+fn device_model() {
+    round_trip::<josev::DeviceModelResponse>(
+        "device_model_response",
+        json!({
+            "model": "PNX-22",
+            "vendor": "IoT.bzh",
+            "identity": "CS0001",
+            "ocpp_csms_url": "wss://csms.example.com/ocpp",
+            "security_profile": 2,
+            "basic_auth_password": "secret",
+            "serial_number": "SN0001",
+            "firmware_version": "1.0.0",
+            "sim_iccid": "8933150319000000000",
+            "evses": [{
+                "ocpp_id": 1,
+                "iso15118_id": "DE*PNX*E12345*1",
+                "power_kw": 22.0,
+                "supply_phases": 3,
+                "connectors": [{"id": 1, "connector_type": "cType2"}, {"id": 2, "connector_type": "s309-3P-32A"}],
+            }],
+            "components": [{
+                "name": "EVSE",
+                "evse_id": 1,
+                "variables": [
+                    {"name": "Power", "value": "22000", "unit": "W", "mutability": "ReadOnly", "data_type": "decimal"},
+                    {"name": "Available", "value": "true", "constant": true, "data_type": "boolean"},
+                ],
+            }, {
+                "name": "Connector",
+                "instance": "left",
+                "connector_id": 1,
+            }],
+        }),
+    );
+    round_trip::<josev::DeviceModelResponse>(
+        "device_model_response",
+        json!({
+            "model": "PNX-7",
+            "vendor": "IoT.bzh",
+            "identity": "CS0002",
+            "ocpp_csms_url": "ws://csms.example.com/ocpp",
+            "security_profile": 0,
+            "basic_auth_password": "",
+            "serial_number": "SN0002",
+            "firmware_version": "1.0.0",
+            "evses": [],
+        }),
+    );
+}
+
+#[test]
+fn generated_schemas_carry_the_constraints() {
+    let schema = |root: schemars::schema::RootSchema, field: &str| {
+        serde_json::to_value(root).unwrap()["properties"][field].clone()
+    };
+
+    let duty_cycle = schema(schema_for!(josev::CpStatusUpdate), "duty_cycle");
+    assert_eq!(duty_cycle["minimum"], 0.0);
+    assert_eq!(duty_cycle["maximum"], 100.0);
+    let power_factor = schema(schema_for!(josev::MeterValuesResponse), "power_factor");
+    assert_eq!(power_factor["minimum"], 0.0);
+    assert_eq!(power_factor["maximum"], 1.0);
+    let current = schema(schema_for!(josev::CpPwmRequest), "current");
+    assert_eq!(current["minimum"], 0.0);
+}
+
+#[test]
+fn verb_arguments() {
+    // the schemas given by the info verb are the ones the verbs parse
+    let parse = |schema: schemars::schema::RootSchema, arg: Value| {
+        let valid = JSONSchema::options()
+            .with_draft(Draft::Draft7)
+            .compile(&serde_json::to_value(schema).unwrap())
+            .unwrap()
+            .is_valid(&arg);
+        (valid, arg)
+    };
+
+    let (valid, arg) = parse(
+        schema_for!(args::AvailabilityArgs),
+        json!({"operational_status": "inoperative"}),
+    );
+    assert!(valid);
+    let availability: args::AvailabilityArgs = serde_json::from_value(arg).unwrap();
+    assert_eq!(
+        availability.operational_status,
+        Some(josev::OperationalStatus::Inoperative)
+    );
+    let (valid, arg) = parse(
+        schema_for!(args::AvailabilityArgs),
+        json!({"operational_status": "broken"}),
+    );
+    assert!(!valid);
+    assert!(serde_json::from_value::<args::AvailabilityArgs>(arg).is_err());
+
+    let (valid, arg) = parse(schema_for!(args::ForceCpStateArgs), json!({"cp": "C2"}));
+    assert!(valid);
+    let force: args::ForceCpStateArgs = serde_json::from_value(arg).unwrap();
+    assert_eq!(force.cp, Some(josev::ControlPilotState::C2));
+    assert_eq!(force.closed_contactor, None);
+
+    let (valid, arg) = parse(schema_for!(args::MqttConfigArgs), json!({"port": 70000}));
+    assert!(!valid);
+    assert!(serde_json::from_value::<args::MqttConfigArgs>(arg).is_err());
+    let (valid, _) = parse(schema_for!(args::MqttConfigArgs), json!({"port": 0}));
+    assert!(!valid);
+}
+
+#[test]
+fn out_of_range_values_are_rejected() {
+    let cp_status = |duty_cycle: f64| {
+        serde_json::from_value::<josev::CpStatusUpdate>(
+            json!({"evse_id": "DE*PNX*E12345*1", "connector_id": 1, "state": "B2", "duty_cycle": duty_cycle}),
+        )
+    };
+    assert!(cp_status(100.0).is_ok());
+    assert!(cp_status(100.5).is_err());
+    assert!(cp_status(-1.0).is_err());
+
+    let meter_values = |power_factor: f64| {
+        serde_json::from_value::<josev::MeterValuesResponse>(json!({
+            "timestamp": "2024-05-14T10:00:00Z",
+            "voltage": {"l1": 230.0, "l2": 230.0, "l3": 230.0},
+            "current": {"l1": 0.0, "l2": 0.0, "l3": 0.0},
+            "power_factor": power_factor,
+            "frequency": 50.0,
+            "total_active_energy_imported": 0.0,
+            "total_reactive_energy_imported": 0.0,
+        }))
+    };
+    assert!(meter_values(1.0).is_ok());
+    assert!(meter_values(1.5).is_err());
+    assert!(meter_values(-0.5).is_err());
+
+    let cp_pwm = |current: f64| {
+        serde_json::from_value::<josev::CpPwmRequest>(
+            json!({"evse_id": "DE*PNX*E12345*1", "hlc": false, "current": current, "error_state": false, "fault_state": false}),
+        )
+    };
+    assert!(cp_pwm(0.0).is_ok());
+    assert!(cp_pwm(-6.0).is_err());
+}
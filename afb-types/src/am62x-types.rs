@@ -21,11 +21,23 @@ pub enum Iec6185Msg {
     CableImax(u32),
     RelayOn(bool),
     Error(String),
+    CpMeasure(CpMeasure),
+}
+
+// Control pilot levels as sampled by the CP ADC (volts and percent)
+AfbDataConverter!(cp_measure, CpMeasure);
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub struct CpMeasure {
+    pub max_voltage: f32,
+    pub min_voltage: f32,
+    pub duty_cycle: f32,
 }
 
 
 pub fn am62x_registers() -> Result <(), AfbError> {
     // add binding custom converter
     iec6185_msg::register()?;
+    cp_measure::register()?;
     Ok(())
 }
\ No newline at end of file
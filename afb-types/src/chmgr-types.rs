@@ -58,7 +58,7 @@ pub enum PlugState {
     Unknown,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum IsoState {
     Iso20,
@@ -69,7 +69,7 @@ pub enum IsoState {
     Unset,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum PaymentOption {
     Eim,
@@ -107,6 +107,10 @@ pub enum ChargingMsg {
     Protocol(ChargingProtocol),
     Payment(PaymentOption),
     ServiceStatus { name: String, status: ServiceStatus },
+    TransactionStarted { id: Option<String> },
+    TransactionUpdated { id: Option<String> },
+    TransactionEnded { id: Option<String>, reason: Option<String> },
+    EmergencyStop { reason: String },
 }
 
 AfbDataConverter!(reservation_state, ReservationState);
@@ -30,9 +30,13 @@ mod auth;
 #[path = "slac-types.rs"]
 mod slac;
 
+#[path = "am62x-types.rs"]
+mod am62x;
+
 pub mod prelude {
     pub use crate::chmgr::*;
     pub use crate::engy::*;
     pub use crate::auth::*;
     pub use crate::slac::*;
+    pub use crate::am62x::*;
 }